@@ -0,0 +1,361 @@
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::{ModelError, Severity};
+
+/// Name of the project config file, it is looked up in the directory of the
+/// model and all of its parents.
+pub const CONFIG_FILE_NAME: &str = ".openfgarc";
+
+/// Codes of the checks finding errors in the model
+const ERROR_RULES: RangeInclusive<u64> = 201..=216;
+/// Codes of the lints
+const LINT_RULES: RangeInclusive<u64> = 301..=310;
+
+/// Rules which only run if the config sets their level. Unused relations
+/// (301) are mostly permissions, which are queried but never referenced.
+const OFF_BY_DEFAULT: [u64; 1] = [301];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse config file: {0}")]
+    Syntax(#[from] serde_yaml::Error),
+    #[error("Rule {0} is not a valid rule code")]
+    InvalidRuleCode(String),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    #[serde(rename = "snake_case")]
+    SnakeCase,
+    #[serde(rename = "camelCase")]
+    CamelCase,
+    #[serde(rename = "PascalCase")]
+    PascalCase,
+}
+
+impl Case {
+    pub fn matches(&self, name: &str) -> bool {
+        let mut chars = name.chars();
+        let first = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+        match self {
+            Case::SnakeCase => {
+                first.is_ascii_lowercase()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+                    && !name.ends_with('_')
+                    && !name.contains("__")
+            }
+            Case::CamelCase => {
+                first.is_ascii_lowercase() && chars.all(|c| c.is_ascii_alphanumeric())
+            }
+            Case::PascalCase => {
+                first.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric())
+            }
+        }
+    }
+}
+
+impl Case {
+    /// Converts the name to the case, words are separated by underscores and
+    /// uppercase letters following a lowercase letter or digit.
+    pub fn convert(&self, name: &str) -> String {
+        let mut words: Vec<String> = Vec::new();
+        let mut previous: Option<char> = None;
+        for c in name.chars() {
+            if c == '_' {
+                previous = None;
+                continue;
+            }
+            let boundary = c.is_ascii_uppercase()
+                && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit());
+            match words.last_mut() {
+                Some(word) if previous.is_some() && !boundary => word.push(c.to_ascii_lowercase()),
+                _ => words.push(c.to_ascii_lowercase().to_string()),
+            }
+            previous = Some(c);
+        }
+        match self {
+            Case::SnakeCase => words.join("_"),
+            Case::CamelCase => words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+                .collect(),
+            Case::PascalCase => words.iter().map(|w| capitalize(w)).collect(),
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Plurals which don't follow the suffix rules of [`singularize`].
+const IRREGULAR_PLURALS: [(&str, &str); 5] = [
+    ("people", "person"),
+    ("children", "child"),
+    ("men", "man"),
+    ("women", "woman"),
+    ("mice", "mouse"),
+];
+
+/// Returns the singular form of an english plural, or the name itself if it
+/// doesn't look like one.
+pub fn singularize(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if let Some((_, singular)) = IRREGULAR_PLURALS
+        .iter()
+        .find(|(plural, _)| *plural == lower)
+    {
+        if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            capitalize(singular)
+        } else {
+            singular.to_string()
+        }
+    } else if lower.ends_with("ies") && name.len() > 3 {
+        format!("{}y", &name[..name.len() - 3])
+    } else if ["sses", "tuses", "xes", "ches", "shes"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        name[..name.len() - 2].to_string()
+    } else if lower.ends_with('s')
+        && !["ss", "us", "is"]
+            .iter()
+            .any(|suffix| lower.ends_with(suffix))
+        && name.len() > 1
+    {
+        name[..name.len() - 1].to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Case::SnakeCase => write!(f, "snake_case"),
+            Case::CamelCase => write!(f, "camelCase"),
+            Case::PascalCase => write!(f, "PascalCase"),
+        }
+    }
+}
+
+/// Naming conventions enforced by the naming lints, nothing is enforced for
+/// conventions which are not set.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamingConventions {
+    pub types: Option<Case>,
+    pub relations: Option<Case>,
+    /// Requires type names to be singular, like `user` instead of `users`
+    pub singular_types: bool,
+    /// Prefix required for relations which can't be assigned directly, like
+    /// `can_`
+    pub permission_prefix: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    rules: BTreeMap<String, RuleLevel>,
+    naming: NamingConventions,
+}
+
+/// Project configuration, written in yaml or json:
+///
+/// ```yaml
+/// rules:
+///   301: info
+///   304: error
+/// naming:
+///   relations: snake_case
+///   singular_types: true
+///   permission_prefix: can_
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Level by rule code, rules which are not listed keep their default
+    /// severity
+    pub rules: BTreeMap<u64, RuleLevel>,
+    pub naming: NamingConventions,
+}
+
+impl Config {
+    pub fn parse(src: &str) -> Result<Config, ConfigError> {
+        // An empty file is a valid config which changes nothing
+        if src.trim().is_empty() {
+            return Ok(Config::default());
+        }
+        let raw: RawConfig = serde_yaml::from_str(src)?;
+        let rules = raw
+            .rules
+            .into_iter()
+            .map(|(code, level)| match code.parse() {
+                Ok(number) if ERROR_RULES.contains(&number) || LINT_RULES.contains(&number) => {
+                    Ok((number, level))
+                }
+                _ => Err(ConfigError::InvalidRuleCode(code)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Config {
+            rules,
+            naming: raw.naming,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        Config::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Loads the config file applying to the model at the given path, falls
+    /// back to the default config if there is none.
+    pub fn load_for(model_path: &Path) -> Result<Config, ConfigError> {
+        match find_config(model_path) {
+            Some(path) => Config::load(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Returns the severity the error should be reported with, `None` if its
+    /// rule is turned off.
+    pub fn severity(&self, error: &ModelError) -> Option<Severity> {
+        match self.rules.get(&error.get_code()) {
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::Error) => Some(Severity::Error),
+            Some(RuleLevel::Warning) => Some(Severity::Warning),
+            Some(RuleLevel::Info) => Some(Severity::Info),
+            None if OFF_BY_DEFAULT.contains(&error.get_code()) => None,
+            None => Some(error.get_severity()),
+        }
+    }
+}
+
+/// Searches the config file for the model at the given path, starting in its
+/// directory and walking up to the root.
+pub fn find_config(model_path: &Path) -> Option<PathBuf> {
+    let model_path = fs::canonicalize(model_path).unwrap_or_else(|_| model_path.to_path_buf());
+    let start = if model_path.is_dir() {
+        model_path.as_path()
+    } else {
+        model_path.parent()?
+    };
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules_and_naming() {
+        let config = Config::parse(
+            "rules:\n  301: info\n  304: off\n  205: error\nnaming:\n  types: snake_case\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.rules,
+            BTreeMap::from([
+                (205, RuleLevel::Error),
+                (301, RuleLevel::Info),
+                (304, RuleLevel::Off)
+            ])
+        );
+        assert_eq!(config.naming.types, Some(Case::SnakeCase));
+    }
+
+    #[test]
+    fn rejects_unknown_rules() {
+        for src in [
+            "rules:\n  999: off\n",
+            "rules:\n  3: off\n",
+            "rules:\n  abc: off\n",
+        ] {
+            assert!(matches!(
+                Config::parse(src),
+                Err(ConfigError::InvalidRuleCode(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn configures_errors_like_lints() {
+        let config = Config::parse("rules:\n  201: off\n  211: warning\n").unwrap();
+        assert_eq!(
+            config.rules,
+            BTreeMap::from([(201, RuleLevel::Off), (211, RuleLevel::Warning)])
+        );
+    }
+
+    #[test]
+    fn matches_and_converts_cases() {
+        let cases = [
+            (Case::SnakeCase, "can_view_doc"),
+            (Case::CamelCase, "canViewDoc"),
+            (Case::PascalCase, "CanViewDoc"),
+        ];
+        for (case, expected) in cases {
+            assert!(case.matches(expected), "{case} {expected}");
+            for (_, name) in cases {
+                assert_eq!(case.convert(name), expected, "{case} {name}");
+            }
+        }
+        assert!(!Case::SnakeCase.matches("can__view"));
+        assert!(!Case::SnakeCase.matches("can_view_"));
+        assert!(!Case::CamelCase.matches("can_view"));
+        assert!(!Case::PascalCase.matches("canView"));
+        assert!(!Case::SnakeCase.matches(""));
+        assert_eq!(Case::SnakeCase.convert("viewer2Doc"), "viewer2_doc");
+        assert_eq!(Case::PascalCase.convert("__doc"), "Doc");
+    }
+
+    #[test]
+    fn singularizes_plurals() {
+        for (plural, singular) in [
+            ("users", "user"),
+            ("policies", "policy"),
+            ("addresses", "address"),
+            ("statuses", "status"),
+            ("boxes", "box"),
+            ("branches", "branch"),
+            ("wishes", "wish"),
+            ("databases", "database"),
+            ("people", "person"),
+            ("Children", "Child"),
+        ] {
+            assert_eq!(singularize(plural), singular);
+        }
+        for name in ["user", "status", "analysis", "access", "s"] {
+            assert_eq!(singularize(name), name);
+        }
+    }
+}
@@ -1,7 +1,20 @@
-use openfga_common::{Access, AuthorizationModel, Identifier, Relation, Type};
-use std::{collections::HashMap, ops::Range};
+use openfga_common::{
+    Access, AuthorizationModel, Condition, ConditionParameter, FileId, Identifier, ParameterType,
+    Relation, Span, Type, TypeRestriction,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
 use thiserror::Error;
 
+mod config;
+
+pub use config::{
+    find_config, singularize, Case, Config, ConfigError, NamingConventions, RuleLevel,
+    CONFIG_FILE_NAME,
+};
+
 #[derive(Debug, Error)]
 pub enum ModelError {
     #[error("Type with name {} was defined twice", type1.identifier.name)]
@@ -23,6 +36,7 @@ pub enum ModelError {
         access: Access,
         relation: Relation,
         target_type: Type,
+        suggestions: Vec<String>,
     },
     #[error("Relation definition {} on type {} references itself",
     relation.identifier.name,
@@ -33,6 +47,185 @@ pub enum ModelError {
         relation: Relation,
         target_type: Type,
     },
+    #[error("Relation definition {} on type {} allows type {}, which does not exist",
+    relation.identifier.name,
+    target_type.identifier.name,
+    restriction.type_identifier.name)]
+    UnknownRestrictionType {
+        restriction: TypeRestriction,
+        relation: Relation,
+        target_type: Type,
+        suggestions: Vec<String>,
+    },
+    #[error("Relation definition {} on type {} allows {}, but relation {} does not exist on type {}",
+    relation.identifier.name,
+    target_type.identifier.name,
+    restriction,
+    restriction.relation.as_ref().unwrap().name,
+    restriction.type_identifier.name)]
+    UnknownRestrictionRelation {
+        restriction: TypeRestriction,
+        relation: Relation,
+        target_type: Type,
+        suggestions: Vec<String>,
+    },
+    #[error("Relation definition {} on type {} allows wildcard {}, which can't reference a relation",
+    relation.identifier.name,
+    target_type.identifier.name,
+    restriction)]
+    InvalidWildcardRestriction {
+        restriction: TypeRestriction,
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error("Relation definition {} on type {} references condition {}, which is not defined",
+    relation.identifier.name,
+    target_type.identifier.name,
+    condition_identifier.name)]
+    UndefinedCondition {
+        condition_identifier: Identifier,
+        restriction: TypeRestriction,
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error("Condition {} is defined but never used", condition.identifier.name)]
+    UnusedCondition { condition: Condition },
+    #[error("Parameter {} of condition {} has invalid type {}",
+    parameter.identifier.name,
+    condition.identifier.name,
+    parameter_type)]
+    InvalidParameterType {
+        parameter_type: ParameterType,
+        parameter: ConditionParameter,
+        condition: Condition,
+    },
+    #[error("Relations {} form a cycle which can never be resolved", cycle)]
+    CyclicRelation { cycle: RelationCycle },
+    #[error("Relation definition {} on type {} references tupleset {}, which does not exist",
+    relation.identifier.name,
+    target_type.identifier.name,
+    tupleset_identifier.name)]
+    UnknownTupleset {
+        tupleset_identifier: Identifier,
+        access: Access,
+        relation: Relation,
+        target_type: Type,
+        suggestions: Vec<String>,
+    },
+    #[error("Relation definition {} on type {} references relation {} from {}, but none of the types assignable to {} define it",
+    relation.identifier.name,
+    target_type.identifier.name,
+    relation_identifier.name,
+    tupleset.identifier.name,
+    tupleset.identifier.name)]
+    UnknownTuplesetRelation {
+        relation_identifier: Identifier,
+        tupleset: Relation,
+        candidate_types: Vec<String>,
+        access: Access,
+        relation: Relation,
+        target_type: Type,
+        suggestions: Vec<String>,
+    },
+    #[error("Relation definition {} on type {} uses {} as tupleset, but it is not a directly assignable relation without usersets",
+    relation.identifier.name,
+    target_type.identifier.name,
+    tupleset.identifier.name)]
+    InvalidTupleset {
+        tupleset_identifier: Identifier,
+        tupleset: Relation,
+        access: Access,
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error("Condition with name {} was defined twice", condition1.identifier.name)]
+    DuplicateConditionName {
+        condition1: Condition,
+        condition2: Condition,
+    },
+    #[error("Type {} is extended, but it is not defined in any module",
+    target_type.identifier.name)]
+    UnknownExtendedType { target_type: Type },
+    #[error("Relation {} on type {} is never referenced",
+    relation.identifier.name,
+    target_type.identifier.name)]
+    UnusedRelation {
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error("Type {} has no relations and is never used in a type restriction",
+    target_type.identifier.name)]
+    TypeWithoutRelations { target_type: Type },
+    #[error("Relation {} on type {} is only referenced through but not",
+    relation.identifier.name,
+    target_type.identifier.name)]
+    ExcludedOnlyRelation {
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error("Relation definition {} on type {} contains {} more than once in a union",
+    relation.identifier.name,
+    target_type.identifier.name,
+    member)]
+    RedundantUnionMember {
+        member: Access,
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error("Relation definition {} on type {} contains {}, which can never be satisfied",
+    relation.identifier.name,
+    target_type.identifier.name,
+    access)]
+    UnsatisfiableAccess {
+        access: Access,
+        relation: Relation,
+        target_type: Type,
+    },
+    #[error(
+        "Relations {} form a cycle, which is resolved through a direct assignment",
+        cycle
+    )]
+    GuardedCyclicRelation { cycle: RelationCycle },
+    #[error("Type {} does not follow the {} naming convention",
+    target_type.identifier.name,
+    convention)]
+    TypeNamingConvention {
+        target_type: Type,
+        convention: Case,
+        suggestion: String,
+    },
+    #[error("Relation {} on type {} does not follow the {} naming convention",
+    relation.identifier.name,
+    target_type.identifier.name,
+    convention)]
+    RelationNamingConvention {
+        relation: Relation,
+        target_type: Type,
+        convention: Case,
+        suggestion: String,
+    },
+    #[error("Type {} should be named in singular form", target_type.identifier.name)]
+    PluralTypeName {
+        target_type: Type,
+        suggestion: String,
+    },
+    #[error("Relation {} on type {} can't be assigned directly, so it should start with {}",
+    relation.identifier.name,
+    target_type.identifier.name,
+    prefix)]
+    MissingPermissionPrefix {
+        relation: Relation,
+        target_type: Type,
+        prefix: String,
+        suggestion: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
 }
 
 impl ModelError {
@@ -49,6 +242,7 @@ impl ModelError {
                 access: _,
                 relation: _,
                 target_type: _,
+                suggestions: _,
             } => 203,
             Self::SelfReferencingRelation {
                 relation_identifier: _,
@@ -56,10 +250,192 @@ impl ModelError {
                 relation: _,
                 target_type: _,
             } => 204,
+            Self::UnknownRestrictionType {
+                restriction: _,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => 205,
+            Self::UnknownRestrictionRelation {
+                restriction: _,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => 206,
+            Self::InvalidWildcardRestriction {
+                restriction: _,
+                relation: _,
+                target_type: _,
+            } => 207,
+            Self::UndefinedCondition {
+                condition_identifier: _,
+                restriction: _,
+                relation: _,
+                target_type: _,
+            } => 208,
+            Self::UnusedCondition { condition: _ } => 209,
+            Self::InvalidParameterType {
+                parameter_type: _,
+                parameter: _,
+                condition: _,
+            } => 210,
+            Self::CyclicRelation { cycle: _ } => 211,
+            Self::UnknownTupleset {
+                tupleset_identifier: _,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => 212,
+            Self::UnknownTuplesetRelation {
+                relation_identifier: _,
+                tupleset: _,
+                candidate_types: _,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => 213,
+            Self::InvalidTupleset {
+                tupleset_identifier: _,
+                tupleset: _,
+                access: _,
+                relation: _,
+                target_type: _,
+            } => 214,
+            Self::DuplicateConditionName {
+                condition1: _,
+                condition2: _,
+            } => 215,
+            Self::UnknownExtendedType { target_type: _ } => 216,
+            Self::UnusedRelation {
+                relation: _,
+                target_type: _,
+            } => 301,
+            Self::TypeWithoutRelations { target_type: _ } => 302,
+            Self::ExcludedOnlyRelation {
+                relation: _,
+                target_type: _,
+            } => 303,
+            Self::RedundantUnionMember {
+                member: _,
+                relation: _,
+                target_type: _,
+            } => 304,
+            Self::UnsatisfiableAccess {
+                access: _,
+                relation: _,
+                target_type: _,
+            } => 305,
+            Self::GuardedCyclicRelation { cycle: _ } => 306,
+            Self::TypeNamingConvention {
+                target_type: _,
+                convention: _,
+                suggestion: _,
+            } => 307,
+            Self::RelationNamingConvention {
+                relation: _,
+                target_type: _,
+                convention: _,
+                suggestion: _,
+            } => 308,
+            Self::PluralTypeName {
+                target_type: _,
+                suggestion: _,
+            } => 309,
+            Self::MissingPermissionPrefix {
+                relation: _,
+                target_type: _,
+                prefix: _,
+                suggestion: _,
+            } => 310,
         }
     }
 
-    pub fn get_span(&self) -> Range<usize> {
+    /// Default severity of the error, lints reported by `lint_model` and
+    /// `lint_naming` are warnings or infos, everything found by `check_model`
+    /// is an error.
+    pub fn get_severity(&self) -> Severity {
+        match self.get_code() {
+            301 | 303 | 306 => Severity::Info,
+            302 | 304 | 305 | 307..=310 => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Returns the name the identifier of the naming lint should be renamed
+    /// to.
+    pub fn get_suggestion(&self) -> Option<&str> {
+        match self {
+            Self::TypeNamingConvention {
+                target_type: _,
+                convention: _,
+                suggestion,
+            }
+            | Self::RelationNamingConvention {
+                relation: _,
+                target_type: _,
+                convention: _,
+                suggestion,
+            }
+            | Self::PluralTypeName {
+                target_type: _,
+                suggestion,
+            }
+            | Self::MissingPermissionPrefix {
+                relation: _,
+                target_type: _,
+                prefix: _,
+                suggestion,
+            } => Some(suggestion),
+            _ => None,
+        }
+    }
+
+    /// Returns the existing names close to the unknown name the error
+    /// reports, closest first.
+    pub fn get_similar_names(&self) -> &[String] {
+        match self {
+            Self::UnknownRelation {
+                relation_identifier: _,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions,
+            }
+            | Self::UnknownRestrictionType {
+                restriction: _,
+                relation: _,
+                target_type: _,
+                suggestions,
+            }
+            | Self::UnknownRestrictionRelation {
+                restriction: _,
+                relation: _,
+                target_type: _,
+                suggestions,
+            }
+            | Self::UnknownTupleset {
+                tupleset_identifier: _,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions,
+            }
+            | Self::UnknownTuplesetRelation {
+                relation_identifier: _,
+                tupleset: _,
+                candidate_types: _,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions,
+            } => suggestions,
+            _ => &[],
+        }
+    }
+
+    pub fn get_span(&self) -> Span {
         match self {
             Self::DuplicateTypeName { type1: _, type2 } => type2.span.clone().unwrap(),
             Self::DuplicateRelationName {
@@ -72,6 +448,7 @@ impl ModelError {
                 access: _,
                 relation: _,
                 target_type: _,
+                suggestions: _,
             } => relation_identifier.span.clone().unwrap(),
             Self::SelfReferencingRelation {
                 relation_identifier,
@@ -79,10 +456,1049 @@ impl ModelError {
                 relation: _,
                 target_type: _,
             } => relation_identifier.span.clone().unwrap(),
+            Self::UnknownRestrictionType {
+                restriction,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => restriction.type_identifier.span.clone().unwrap(),
+            Self::UnknownRestrictionRelation {
+                restriction,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => restriction.relation.as_ref().unwrap().span.clone().unwrap(),
+            Self::InvalidWildcardRestriction {
+                restriction,
+                relation: _,
+                target_type: _,
+            } => restriction.span.clone().unwrap(),
+            Self::UndefinedCondition {
+                condition_identifier,
+                restriction: _,
+                relation: _,
+                target_type: _,
+            } => condition_identifier.span.clone().unwrap(),
+            Self::UnusedCondition { condition } => condition.identifier.span.clone().unwrap(),
+            Self::InvalidParameterType {
+                parameter_type,
+                parameter: _,
+                condition: _,
+            } => parameter_type.span.clone().unwrap(),
+            Self::CyclicRelation { cycle } => cycle.steps[0].reference.span.clone().unwrap(),
+            Self::UnknownTupleset {
+                tupleset_identifier,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => tupleset_identifier.span.clone().unwrap(),
+            Self::UnknownTuplesetRelation {
+                relation_identifier,
+                tupleset: _,
+                candidate_types: _,
+                access: _,
+                relation: _,
+                target_type: _,
+                suggestions: _,
+            } => relation_identifier.span.clone().unwrap(),
+            Self::InvalidTupleset {
+                tupleset_identifier,
+                tupleset: _,
+                access: _,
+                relation: _,
+                target_type: _,
+            } => tupleset_identifier.span.clone().unwrap(),
+            Self::DuplicateConditionName {
+                condition1: _,
+                condition2,
+            } => condition2.identifier.span.clone().unwrap(),
+            Self::UnknownExtendedType { target_type } => {
+                target_type.identifier.span.clone().unwrap()
+            }
+            Self::UnusedRelation {
+                relation,
+                target_type: _,
+            } => relation.identifier.span.clone().unwrap(),
+            Self::TypeWithoutRelations { target_type } => {
+                target_type.identifier.span.clone().unwrap()
+            }
+            Self::ExcludedOnlyRelation {
+                relation,
+                target_type: _,
+            } => relation.identifier.span.clone().unwrap(),
+            Self::RedundantUnionMember {
+                member,
+                relation: _,
+                target_type: _,
+            } => member.get_span().unwrap(),
+            Self::UnsatisfiableAccess {
+                access,
+                relation: _,
+                target_type: _,
+            } => access.get_span().unwrap(),
+            Self::GuardedCyclicRelation { cycle } => cycle.steps[0].reference.span.clone().unwrap(),
+            Self::TypeNamingConvention {
+                target_type,
+                convention: _,
+                suggestion: _,
+            } => target_type.identifier.span.clone().unwrap(),
+            Self::RelationNamingConvention {
+                relation,
+                target_type: _,
+                convention: _,
+                suggestion: _,
+            } => relation.identifier.span.clone().unwrap(),
+            Self::PluralTypeName {
+                target_type,
+                suggestion: _,
+            } => target_type.identifier.span.clone().unwrap(),
+            Self::MissingPermissionPrefix {
+                relation,
+                target_type: _,
+                prefix: _,
+                suggestion: _,
+            } => relation.identifier.span.clone().unwrap(),
         }
     }
 }
 
+/// Reference inside of `relation` on `type_name` pointing to the next relation
+/// of a cycle.
+#[derive(Debug, Clone)]
+pub struct CycleStep {
+    pub type_name: String,
+    pub relation: Relation,
+    pub reference: Identifier,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelationCycle {
+    pub steps: Vec<CycleStep>,
+    /// A cycle is harmless if one of its relations can be resolved without
+    /// following the cycle, e.g. through a direct assignment in a union.
+    pub fatal: bool,
+}
+
+impl fmt::Display for RelationCycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for step in &self.steps {
+            write!(
+                f,
+                "{}#{} -> ",
+                step.type_name, step.relation.identifier.name
+            )?;
+        }
+        let first = &self.steps[0];
+        write!(f, "{}#{}", first.type_name, first.relation.identifier.name)
+    }
+}
+
+/// Returns the number of single character insertions, deletions and
+/// substitutions needed to turn one name into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Returns the candidates which are close enough to the name to be likely
+/// meant instead of it, closest first.
+pub fn similar_names<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let max_distance = name.chars().count().max(3) / 3;
+    let mut similar: Vec<(usize, &str)> = candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    similar.sort();
+    similar.dedup();
+    similar
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Returns true if the relation can be the tupleset of a tuple to userset,
+/// which requires it to only be directly assignable to plain types.
+pub fn is_tupleset(relation: &Relation) -> bool {
+    match &relation.access {
+        Access::Direct {
+            restrictions,
+            span: _,
+        } => restrictions.iter().all(|r| r.relation.is_none()),
+        _ => false,
+    }
+}
+
+/// Returns the names of the types which can be assigned to the tupleset
+/// relation of the type. Without restrictions (schema 1.0 `self`) every type
+/// is assignable.
+pub fn tupleset_types(model: &AuthorizationModel, rtype: &Type, tupleset: &str) -> Vec<String> {
+    let restrictions = rtype
+        .relations
+        .iter()
+        .find(|r| r.identifier.name == tupleset)
+        .map(|r| r.type_restrictions())
+        .unwrap_or_default();
+    let mut types: Vec<String> = if restrictions.is_empty() {
+        model
+            .types
+            .iter()
+            .map(|t| t.identifier.name.clone())
+            .collect()
+    } else {
+        restrictions
+            .iter()
+            .map(|r| r.type_identifier.name.clone())
+            .collect()
+    };
+    types.sort();
+    types.dedup();
+    types
+}
+
+/// Returns every identifier referencing the type, excluding its definition.
+pub fn type_references(model: &AuthorizationModel, type_name: &str) -> Vec<Identifier> {
+    model
+        .types
+        .iter()
+        .flat_map(|t| t.relations.iter())
+        .flat_map(|r| r.type_restrictions())
+        .filter(|r| r.type_identifier.name == type_name)
+        .map(|r| r.type_identifier.clone())
+        .collect()
+}
+
+/// Returns every identifier referencing the condition, excluding its
+/// definition.
+pub fn condition_references(model: &AuthorizationModel, condition_name: &str) -> Vec<Identifier> {
+    model
+        .types
+        .iter()
+        .flat_map(|t| t.relations.iter())
+        .flat_map(|r| r.type_restrictions())
+        .filter_map(|r| r.condition.as_ref())
+        .filter(|c| c.name == condition_name)
+        .cloned()
+        .collect()
+}
+
+/// Returns every identifier referencing the relation of the type, excluding
+/// its definition.
+pub fn relation_references(
+    model: &AuthorizationModel,
+    type_name: &str,
+    relation_name: &str,
+) -> Vec<Identifier> {
+    fn collect(
+        access: &Access,
+        rtype: &Type,
+        model: &AuthorizationModel,
+        target: (&str, &str),
+        references: &mut Vec<Identifier>,
+    ) {
+        let (type_name, relation_name) = target;
+        let on_type = rtype.identifier.name == type_name;
+        match access {
+            Access::Direct {
+                restrictions,
+                span: _,
+            } => restrictions
+                .iter()
+                .filter(|r| r.type_identifier.name == type_name)
+                .filter_map(|r| r.relation.as_ref())
+                .filter(|r| r.name == relation_name)
+                .for_each(|r| references.push(r.clone())),
+            Access::SelfComputed { relation, span: _ } => {
+                if on_type && relation.name == relation_name {
+                    references.push(relation.clone());
+                }
+            }
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => {
+                if on_type && object.name == relation_name {
+                    references.push(object.clone());
+                }
+                if relation.name == relation_name
+                    && tupleset_types(model, rtype, &object.name)
+                        .iter()
+                        .any(|t| t == type_name)
+                {
+                    references.push(relation.clone());
+                }
+            }
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children
+                    .iter()
+                    .for_each(|c| collect(c, rtype, model, target, references))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => {
+                collect(base, rtype, model, target, references);
+                collect(subtract, rtype, model, target, references);
+            }
+        }
+    }
+
+    let mut references = Vec::new();
+    model.types.iter().for_each(|t| {
+        t.relations.iter().for_each(|r| {
+            collect(
+                &r.access,
+                t,
+                model,
+                (type_name, relation_name),
+                &mut references,
+            )
+        })
+    });
+    references
+}
+
+/// Identifier of the model found at a position of its source.
+#[derive(Debug, Clone, Copy)]
+pub enum Symbol<'a> {
+    /// Name of a type, in its definition or in a type restriction
+    Type(&'a Identifier),
+    /// Name of a relation of the type, in its definition, a computed userset
+    /// or a userset type restriction
+    Relation {
+        identifier: &'a Identifier,
+        type_name: &'a str,
+    },
+    /// `y` of a tuple to userset `x from y` defined on the type
+    Tupleset {
+        identifier: &'a Identifier,
+        rtype: &'a Type,
+    },
+    /// `x` of a tuple to userset `x from y` defined on the type
+    TuplesetRelation {
+        identifier: &'a Identifier,
+        tupleset: &'a Identifier,
+        rtype: &'a Type,
+    },
+    /// Name of a condition, in its definition or behind `with`
+    Condition(&'a Identifier),
+}
+
+impl<'a> Symbol<'a> {
+    pub fn identifier(&self) -> &'a Identifier {
+        match self {
+            Symbol::Type(identifier) | Symbol::Condition(identifier) => identifier,
+            Symbol::Relation {
+                identifier,
+                type_name: _,
+            }
+            | Symbol::Tupleset {
+                identifier,
+                rtype: _,
+            }
+            | Symbol::TuplesetRelation {
+                identifier,
+                tupleset: _,
+                rtype: _,
+            } => identifier,
+        }
+    }
+}
+
+fn contains(identifier: &Identifier, file: FileId, offset: usize) -> bool {
+    identifier
+        .span
+        .as_ref()
+        .is_some_and(|span| span.file == file && span.start <= offset && offset <= span.end)
+}
+
+/// Returns the symbol at the character offset of the source of the model.
+pub fn symbol_at(model: &AuthorizationModel, offset: usize) -> Option<Symbol<'_>> {
+    symbol_at_in_file(model, 0, offset)
+}
+
+/// Returns the symbol at the character offset of one of the files of a model
+/// parsed from several files.
+pub fn symbol_at_in_file(
+    model: &AuthorizationModel,
+    file: FileId,
+    offset: usize,
+) -> Option<Symbol<'_>> {
+    fn find<'a>(
+        access: &'a Access,
+        rtype: &'a Type,
+        file: FileId,
+        offset: usize,
+    ) -> Option<Symbol<'a>> {
+        match access {
+            Access::Direct {
+                restrictions,
+                span: _,
+            } => restrictions.iter().find_map(|r| {
+                if contains(&r.type_identifier, file, offset) {
+                    return Some(Symbol::Type(&r.type_identifier));
+                }
+                if let Some(relation) = r.relation.as_ref().filter(|i| contains(i, file, offset)) {
+                    return Some(Symbol::Relation {
+                        identifier: relation,
+                        type_name: &r.type_identifier.name,
+                    });
+                }
+                r.condition
+                    .as_ref()
+                    .filter(|i| contains(i, file, offset))
+                    .map(Symbol::Condition)
+            }),
+            Access::SelfComputed { relation, span: _ } => contains(relation, file, offset)
+                .then_some(Symbol::Relation {
+                    identifier: relation,
+                    type_name: &rtype.identifier.name,
+                }),
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => {
+                if contains(object, file, offset) {
+                    Some(Symbol::Tupleset {
+                        identifier: object,
+                        rtype,
+                    })
+                } else if contains(relation, file, offset) {
+                    Some(Symbol::TuplesetRelation {
+                        identifier: relation,
+                        tupleset: object,
+                        rtype,
+                    })
+                } else {
+                    None
+                }
+            }
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children.iter().find_map(|c| find(c, rtype, file, offset))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => find(base, rtype, file, offset).or_else(|| find(subtract, rtype, file, offset)),
+        }
+    }
+
+    model
+        .types
+        .iter()
+        .find_map(|t| {
+            if contains(&t.identifier, file, offset) {
+                return Some(Symbol::Type(&t.identifier));
+            }
+            t.relations.iter().find_map(|r| {
+                if contains(&r.identifier, file, offset) {
+                    Some(Symbol::Relation {
+                        identifier: &r.identifier,
+                        type_name: &t.identifier.name,
+                    })
+                } else {
+                    find(&r.access, t, file, offset)
+                }
+            })
+        })
+        .or_else(|| {
+            model
+                .conditions
+                .iter()
+                .find(|c| contains(&c.identifier, file, offset))
+                .map(|c| Symbol::Condition(&c.identifier))
+        })
+}
+
+/// Returns the type and relation names of the relations the symbol refers
+/// to, a relation reached through a tuple to userset resolves on every type
+/// the tupleset can point to which has it.
+fn resolve_relations(model: &AuthorizationModel, symbol: &Symbol) -> Vec<(String, String)> {
+    match symbol {
+        Symbol::Relation {
+            identifier,
+            type_name,
+        } => vec![(type_name.to_string(), identifier.name.clone())],
+        Symbol::Tupleset { identifier, rtype } => {
+            vec![(rtype.identifier.name.clone(), identifier.name.clone())]
+        }
+        Symbol::TuplesetRelation {
+            identifier,
+            tupleset,
+            rtype,
+        } => tupleset_types(model, rtype, &tupleset.name)
+            .into_iter()
+            .filter(|t| model.type_relation_exists(t, &identifier.name))
+            .map(|t| (t, identifier.name.clone()))
+            .collect(),
+        Symbol::Type(_) | Symbol::Condition(_) => Vec::new(),
+    }
+}
+
+/// Returns the identifiers of the definitions the symbol refers to.
+pub fn symbol_definitions(model: &AuthorizationModel, symbol: &Symbol) -> Vec<Identifier> {
+    match symbol {
+        Symbol::Type(identifier) => model
+            .types
+            .iter()
+            .filter(|t| t.identifier.name == identifier.name)
+            .map(|t| t.identifier.clone())
+            .collect(),
+        Symbol::Condition(identifier) => model
+            .conditions
+            .iter()
+            .filter(|c| c.identifier.name == identifier.name)
+            .map(|c| c.identifier.clone())
+            .collect(),
+        _ => resolve_relations(model, symbol)
+            .iter()
+            .flat_map(|(type_name, relation_name)| {
+                model
+                    .types
+                    .iter()
+                    .filter(move |t| &t.identifier.name == type_name)
+                    .flat_map(|t| t.relations.iter())
+                    .filter(move |r| &r.identifier.name == relation_name)
+                    .map(|r| r.identifier.clone())
+            })
+            .collect(),
+    }
+}
+
+/// Returns every identifier referencing the definitions the symbol refers
+/// to, excluding the definitions themselves.
+pub fn symbol_references(model: &AuthorizationModel, symbol: &Symbol) -> Vec<Identifier> {
+    let mut references = match symbol {
+        Symbol::Type(identifier) => type_references(model, &identifier.name),
+        Symbol::Condition(identifier) => condition_references(model, &identifier.name),
+        _ => resolve_relations(model, symbol)
+            .iter()
+            .flat_map(|(type_name, relation_name)| {
+                relation_references(model, type_name, relation_name)
+            })
+            .collect(),
+    };
+    // The relation of a tuple to userset can reference several definitions
+    let mut seen = HashSet::new();
+    references.retain(|identifier| seen.insert(identifier.span.clone()));
+    references
+}
+
+#[derive(Debug, Error)]
+pub enum RenameError {
+    #[error("Type {0} already exists")]
+    TypeExists(String),
+    #[error("Relation {relation} already exists on type {type_name}")]
+    RelationExists { type_name: String, relation: String },
+    #[error("Condition {0} already exists")]
+    ConditionExists(String),
+}
+
+/// Returns every definition and reference which has to be renamed together
+/// with the symbol. A tuple to userset which also resolves to a relation of
+/// another type renames that relation as well, so it keeps resolving.
+pub fn rename_symbol(
+    model: &AuthorizationModel,
+    symbol: &Symbol,
+    new_name: &str,
+) -> Result<Vec<Identifier>, RenameError> {
+    let mut pending = vec![*symbol];
+    let mut seen = HashSet::new();
+    let mut identifiers = Vec::new();
+    while let Some(symbol) = pending.pop() {
+        let name = &symbol.identifier().name;
+        if name != new_name {
+            match symbol {
+                Symbol::Type(_) if model.type_exists(new_name) => {
+                    return Err(RenameError::TypeExists(new_name.to_string()))
+                }
+                Symbol::Condition(_) if model.condition_exists(new_name) => {
+                    return Err(RenameError::ConditionExists(new_name.to_string()))
+                }
+                _ => {
+                    if let Some((type_name, _)) = resolve_relations(model, &symbol)
+                        .into_iter()
+                        .find(|(type_name, _)| model.type_relation_exists(type_name, new_name))
+                    {
+                        return Err(RenameError::RelationExists {
+                            type_name,
+                            relation: new_name.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        for identifier in symbol_definitions(model, &symbol)
+            .into_iter()
+            .chain(symbol_references(model, &symbol))
+        {
+            if !seen.insert(identifier.span.clone()) {
+                continue;
+            }
+            if let Some(reference @ Symbol::TuplesetRelation { .. }) = identifier
+                .span
+                .as_ref()
+                .and_then(|span| symbol_at_in_file(model, span.file, span.start))
+            {
+                pending.push(reference);
+            }
+            identifiers.push(identifier);
+        }
+    }
+    Ok(identifiers)
+}
+
+/// Graph of the relations of a model, with an edge for every relation
+/// referenced by a computed userset, a tuple to userset or a userset type
+/// restriction.
+struct RelationGraph<'a> {
+    model: &'a AuthorizationModel,
+    nodes: Vec<(&'a Type, &'a Relation)>,
+    index: HashMap<(&'a str, &'a str), usize>,
+    edges: Vec<Vec<(usize, Identifier)>>,
+}
+
+impl<'a> RelationGraph<'a> {
+    fn new(model: &'a AuthorizationModel) -> Self {
+        let mut graph = RelationGraph {
+            model,
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            edges: Vec::new(),
+        };
+        for t in &model.types {
+            for r in &t.relations {
+                let key = (t.identifier.name.as_str(), r.identifier.name.as_str());
+                if !graph.index.contains_key(&key) {
+                    graph.index.insert(key, graph.nodes.len());
+                    graph.nodes.push((t, r));
+                }
+            }
+        }
+        graph.edges = (0..graph.nodes.len())
+            .map(|i| {
+                let (t, r) = graph.nodes[i];
+                let mut edges = Vec::new();
+                graph.collect_edges(i, t, &r.access, &mut edges);
+                edges
+            })
+            .collect();
+        graph
+    }
+
+    fn node(&self, type_name: &str, relation_name: &str) -> Option<usize> {
+        self.index.get(&(type_name, relation_name)).copied()
+    }
+
+    /// Returns the nodes reached by `relation from tupleset` on the type.
+    fn tupleset_targets(&self, rtype: &Type, tupleset: &str, relation: &str) -> Vec<usize> {
+        tupleset_types(self.model, rtype, tupleset)
+            .iter()
+            .filter_map(|t| self.node(t, relation))
+            .collect()
+    }
+
+    fn collect_edges(
+        &self,
+        node: usize,
+        rtype: &Type,
+        access: &Access,
+        edges: &mut Vec<(usize, Identifier)>,
+    ) {
+        match access {
+            Access::Direct {
+                restrictions,
+                span: _,
+            } => restrictions.iter().for_each(|r| {
+                if let Some(relation) = &r.relation {
+                    if let Some(target) = self.node(&r.type_identifier.name, &relation.name) {
+                        edges.push((target, relation.clone()));
+                    }
+                }
+            }),
+            Access::SelfComputed { relation, span: _ } => {
+                match self.node(&rtype.identifier.name, &relation.name) {
+                    // Direct self references are reported as SelfReferencingRelation
+                    Some(target) if target != node => edges.push((target, relation.clone())),
+                    _ => (),
+                }
+            }
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => self
+                .tupleset_targets(rtype, &object.name, &relation.name)
+                .into_iter()
+                .for_each(|target| edges.push((target, relation.clone()))),
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children
+                    .iter()
+                    .for_each(|c| self.collect_edges(node, rtype, c, edges))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => {
+                self.collect_edges(node, rtype, base, edges);
+                self.collect_edges(node, rtype, subtract, edges);
+            }
+        }
+    }
+
+    /// Collects the nodes referenced by the access, along with whether the
+    /// reference is only used as the subtracted side of `but not`.
+    fn collect_references(
+        &self,
+        rtype: &Type,
+        access: &Access,
+        excluded: bool,
+        references: &mut Vec<(usize, bool)>,
+    ) {
+        match access {
+            Access::Direct {
+                restrictions,
+                span: _,
+            } => restrictions.iter().for_each(|r| {
+                if let Some(relation) = &r.relation {
+                    if let Some(target) = self.node(&r.type_identifier.name, &relation.name) {
+                        references.push((target, excluded));
+                    }
+                }
+            }),
+            Access::SelfComputed { relation, span: _ } => {
+                if let Some(target) = self.node(&rtype.identifier.name, &relation.name) {
+                    references.push((target, excluded));
+                }
+            }
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => {
+                if let Some(tupleset) = self.node(&rtype.identifier.name, &object.name) {
+                    references.push((tupleset, excluded));
+                }
+                self.tupleset_targets(rtype, &object.name, &relation.name)
+                    .into_iter()
+                    .for_each(|target| references.push((target, excluded)));
+            }
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children
+                    .iter()
+                    .for_each(|c| self.collect_references(rtype, c, excluded, references))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => {
+                self.collect_references(rtype, base, excluded, references);
+                self.collect_references(rtype, subtract, true, references);
+            }
+        }
+    }
+
+    /// Returns true if the access can be resolved without entering any of the
+    /// given nodes.
+    fn escapes(&self, rtype: &Type, access: &Access, component: &HashSet<usize>) -> bool {
+        match access {
+            Access::Direct { .. } => true,
+            Access::SelfComputed { relation, span: _ } => self
+                .node(&rtype.identifier.name, &relation.name)
+                .is_none_or(|target| !component.contains(&target)),
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => {
+                let targets = self.tupleset_targets(rtype, &object.name, &relation.name);
+                targets.is_empty() || targets.iter().any(|t| !component.contains(t))
+            }
+            Access::Union { children, span: _ } => {
+                children.iter().any(|c| self.escapes(rtype, c, component))
+            }
+            Access::Intersection { children, span: _ } => {
+                children.iter().all(|c| self.escapes(rtype, c, component))
+            }
+            Access::Difference {
+                base,
+                subtract: _,
+                span: _,
+            } => self.escapes(rtype, base, component),
+        }
+    }
+
+    /// Tarjan's algorithm, returns the strongly connected components.
+    fn components(&self) -> Vec<Vec<usize>> {
+        struct State {
+            index: usize,
+            indices: Vec<Option<usize>>,
+            lowlinks: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            components: Vec<Vec<usize>>,
+        }
+
+        fn connect(graph: &RelationGraph, node: usize, state: &mut State) {
+            state.indices[node] = Some(state.index);
+            state.lowlinks[node] = state.index;
+            state.index += 1;
+            state.stack.push(node);
+            state.on_stack[node] = true;
+
+            for (target, _) in &graph.edges[node] {
+                match state.indices[*target] {
+                    None => {
+                        connect(graph, *target, state);
+                        state.lowlinks[node] = state.lowlinks[node].min(state.lowlinks[*target]);
+                    }
+                    Some(index) if state.on_stack[*target] => {
+                        state.lowlinks[node] = state.lowlinks[node].min(index);
+                    }
+                    _ => (),
+                }
+            }
+
+            if Some(state.lowlinks[node]) == state.indices[node] {
+                let mut component = Vec::new();
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                state.components.push(component);
+            }
+        }
+
+        let mut state = State {
+            index: 0,
+            indices: vec![None; self.nodes.len()],
+            lowlinks: vec![0; self.nodes.len()],
+            stack: Vec::new(),
+            on_stack: vec![false; self.nodes.len()],
+            components: Vec::new(),
+        };
+        for node in 0..self.nodes.len() {
+            if state.indices[node].is_none() {
+                connect(self, node, &mut state);
+            }
+        }
+        state.components
+    }
+
+    /// Finds the shortest path from the start node back to itself, staying
+    /// inside the component.
+    fn cycle_path(&self, start: usize, component: &HashSet<usize>) -> Vec<CycleStep> {
+        let mut parents: HashMap<usize, (usize, Identifier)> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut last = None;
+        'search: while let Some(node) = queue.pop_front() {
+            for (target, reference) in &self.edges[node] {
+                if *target == start {
+                    last = Some((node, reference.clone()));
+                    break 'search;
+                }
+                if component.contains(target) && !parents.contains_key(target) {
+                    parents.insert(*target, (node, reference.clone()));
+                    queue.push_back(*target);
+                }
+            }
+        }
+
+        let mut steps = Vec::new();
+        let mut current = last;
+        while let Some((node, reference)) = current {
+            let (t, r) = self.nodes[node];
+            steps.push(CycleStep {
+                type_name: t.identifier.name.clone(),
+                relation: r.clone(),
+                reference,
+            });
+            current = if node == start {
+                None
+            } else {
+                parents.get(&node).cloned()
+            };
+        }
+        steps.reverse();
+        steps
+    }
+}
+
+/// Finds the cycles between relations of the model, reporting one cycle per
+/// group of mutually dependent relations.
+pub fn find_relation_cycles(model: &AuthorizationModel) -> Vec<RelationCycle> {
+    let graph = RelationGraph::new(model);
+    graph
+        .components()
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1
+                || graph.edges[component[0]]
+                    .iter()
+                    .any(|(t, _)| *t == component[0])
+        })
+        .map(|component| {
+            let members: HashSet<usize> = component.iter().copied().collect();
+            let fatal = !component.iter().any(|node| {
+                let (t, r) = graph.nodes[*node];
+                graph.escapes(t, &r.access, &members)
+            });
+            RelationCycle {
+                steps: graph.cycle_path(component[0], &members),
+                fatal,
+            }
+        })
+        .collect()
+}
+
+fn check_type_restriction(
+    restriction: &TypeRestriction,
+    relation: &Relation,
+    rtype: &Type,
+    model: &AuthorizationModel,
+    errors: &mut Vec<ModelError>,
+) {
+    if let Some(condition_identifier) = &restriction.condition {
+        if !model.condition_exists(&condition_identifier.name) {
+            errors.push(ModelError::UndefinedCondition {
+                condition_identifier: condition_identifier.clone(),
+                restriction: restriction.clone(),
+                relation: relation.clone(),
+                target_type: rtype.clone(),
+            });
+        }
+    }
+    if !model.type_exists(&restriction.type_identifier.name) {
+        errors.push(ModelError::UnknownRestrictionType {
+            restriction: restriction.clone(),
+            relation: relation.clone(),
+            target_type: rtype.clone(),
+            suggestions: similar_names(
+                &restriction.type_identifier.name,
+                model.types.iter().map(|t| t.identifier.name.as_str()),
+            ),
+        });
+        return;
+    }
+    match &restriction.relation {
+        Some(_) if restriction.wildcard => {
+            errors.push(ModelError::InvalidWildcardRestriction {
+                restriction: restriction.clone(),
+                relation: relation.clone(),
+                target_type: rtype.clone(),
+            });
+        }
+        Some(restriction_relation)
+            if !model.type_relation_exists(
+                &restriction.type_identifier.name,
+                &restriction_relation.name,
+            ) =>
+        {
+            errors.push(ModelError::UnknownRestrictionRelation {
+                restriction: restriction.clone(),
+                relation: relation.clone(),
+                target_type: rtype.clone(),
+                suggestions: similar_names(
+                    &restriction_relation.name,
+                    model
+                        .types
+                        .iter()
+                        .filter(|t| t.identifier.name == restriction.type_identifier.name)
+                        .flat_map(|t| t.relations.iter())
+                        .map(|r| r.identifier.name.as_str()),
+                ),
+            });
+        }
+        _ => (),
+    }
+}
+
+/// Parameter types supported by conditions, the boolean marks generic types
+/// which require a type argument.
+pub const PARAMETER_TYPES: [(&str, bool); 11] = [
+    ("bool", false),
+    ("string", false),
+    ("int", false),
+    ("uint", false),
+    ("double", false),
+    ("duration", false),
+    ("timestamp", false),
+    ("ipaddress", false),
+    ("any", false),
+    ("list", true),
+    ("map", true),
+];
+
+fn check_parameter_type(
+    parameter_type: &ParameterType,
+    parameter: &ConditionParameter,
+    condition: &Condition,
+    errors: &mut Vec<ModelError>,
+) {
+    let generic = PARAMETER_TYPES
+        .iter()
+        .find(|(name, _)| *name == parameter_type.identifier.name)
+        .map(|(_, generic)| *generic);
+    match (generic, &parameter_type.generic) {
+        (Some(true), Some(type_argument)) => {
+            check_parameter_type(type_argument, parameter, condition, errors)
+        }
+        (Some(false), None) => (),
+        _ => errors.push(ModelError::InvalidParameterType {
+            parameter_type: parameter_type.clone(),
+            parameter: parameter.clone(),
+            condition: condition.clone(),
+        }),
+    }
+}
+
+fn check_condition(
+    condition: &Condition,
+    model: &AuthorizationModel,
+    errors: &mut Vec<ModelError>,
+) {
+    condition.parameters.iter().for_each(|p| {
+        check_parameter_type(&p.parameter_type, p, condition, errors);
+    });
+
+    let used = model.types.iter().any(|t| {
+        t.relations.iter().any(|r| {
+            r.type_restrictions().iter().any(|restriction| {
+                restriction
+                    .condition
+                    .as_ref()
+                    .is_some_and(|c| c.name == condition.identifier.name)
+            })
+        })
+    });
+    if !used {
+        errors.push(ModelError::UnusedCondition {
+            condition: condition.clone(),
+        });
+    }
+}
+
 fn check_access(
     access: &Access,
     relation: &Relation,
@@ -91,6 +1507,12 @@ fn check_access(
     errors: &mut Vec<ModelError>,
 ) {
     match access {
+        Access::Direct {
+            restrictions,
+            span: _,
+        } => restrictions
+            .iter()
+            .for_each(|r| check_type_restriction(r, relation, rtype, model, errors)),
         Access::Difference {
             base,
             subtract,
@@ -109,7 +1531,7 @@ fn check_access(
             relation: relation_identifier,
             span: _,
         } => {
-            if &relation_identifier.name == &relation.identifier.name {
+            if relation_identifier.name == relation.identifier.name {
                 errors.push(ModelError::SelfReferencingRelation {
                     relation_identifier: relation_identifier.clone(),
                     access: access.clone(),
@@ -122,21 +1544,352 @@ fn check_access(
                     access: access.clone(),
                     relation: relation.clone(),
                     target_type: rtype.clone(),
+                    suggestions: similar_names(
+                        &relation_identifier.name,
+                        rtype
+                            .relations
+                            .iter()
+                            .map(|r| r.identifier.name.as_str())
+                            .filter(|name| *name != relation.identifier.name),
+                    ),
                 });
             }
         }
-        _ => (),
+        Access::Computed {
+            object,
+            relation: relation_identifier,
+            span: _,
+        } => {
+            let tupleset = match rtype
+                .relations
+                .iter()
+                .find(|r| r.identifier.name == object.name)
+            {
+                Some(tupleset) => tupleset,
+                None => {
+                    errors.push(ModelError::UnknownTupleset {
+                        tupleset_identifier: object.clone(),
+                        access: access.clone(),
+                        relation: relation.clone(),
+                        target_type: rtype.clone(),
+                        suggestions: similar_names(
+                            &object.name,
+                            rtype
+                                .relations
+                                .iter()
+                                .filter(|r| is_tupleset(r))
+                                .map(|r| r.identifier.name.as_str()),
+                        ),
+                    });
+                    return;
+                }
+            };
+            if !is_tupleset(tupleset) {
+                errors.push(ModelError::InvalidTupleset {
+                    tupleset_identifier: object.clone(),
+                    tupleset: tupleset.clone(),
+                    access: access.clone(),
+                    relation: relation.clone(),
+                    target_type: rtype.clone(),
+                });
+                return;
+            }
+            let candidate_types = tupleset_types(model, rtype, &object.name);
+            if !candidate_types
+                .iter()
+                .any(|t| model.type_relation_exists(t, &relation_identifier.name))
+            {
+                let suggestions = similar_names(
+                    &relation_identifier.name,
+                    model
+                        .types
+                        .iter()
+                        .filter(|t| candidate_types.contains(&t.identifier.name))
+                        .flat_map(|t| t.relations.iter())
+                        .map(|r| r.identifier.name.as_str()),
+                );
+                errors.push(ModelError::UnknownTuplesetRelation {
+                    relation_identifier: relation_identifier.clone(),
+                    tupleset: tupleset.clone(),
+                    candidate_types,
+                    access: access.clone(),
+                    relation: relation.clone(),
+                    target_type: rtype.clone(),
+                    suggestions,
+                });
+            }
+        }
+    }
+}
+
+fn lint_access(access: &Access, relation: &Relation, rtype: &Type, lints: &mut Vec<ModelError>) {
+    match access {
+        Access::Union { children, span: _ } => {
+            let mut seen = HashSet::new();
+            children.iter().for_each(|c| {
+                // Accesses are compared by their dsl representation to ignore spans
+                if !seen.insert(c.to_string()) {
+                    lints.push(ModelError::RedundantUnionMember {
+                        member: c.clone(),
+                        relation: relation.clone(),
+                        target_type: rtype.clone(),
+                    });
+                }
+                lint_access(c, relation, rtype, lints);
+            });
+        }
+        Access::Intersection { children, span: _ } => {
+            let operands: HashSet<String> = children.iter().map(|c| c.to_string()).collect();
+            children.iter().for_each(|c| {
+                match c {
+                    // `a and (b but not a)` can never be satisfied
+                    Access::Difference {
+                        base: _,
+                        subtract,
+                        span: _,
+                    } if operands.contains(&subtract.to_string()) => {
+                        lints.push(ModelError::UnsatisfiableAccess {
+                            access: access.clone(),
+                            relation: relation.clone(),
+                            target_type: rtype.clone(),
+                        })
+                    }
+                    _ => (),
+                }
+                lint_access(c, relation, rtype, lints);
+            });
+        }
+        Access::Difference {
+            base,
+            subtract,
+            span: _,
+        } => {
+            if base.to_string() == subtract.to_string() {
+                lints.push(ModelError::UnsatisfiableAccess {
+                    access: access.clone(),
+                    relation: relation.clone(),
+                    target_type: rtype.clone(),
+                });
+            }
+            lint_access(base, relation, rtype, lints);
+            lint_access(subtract, relation, rtype, lints);
+        }
+        Access::Direct { .. } | Access::Computed { .. } | Access::SelfComputed { .. } => (),
+    }
+}
+
+/// Finds constructs which are valid but most likely not intended. The model
+/// is expected to have passed `check_model`.
+pub fn lint_model(model: &AuthorizationModel) -> Vec<ModelError> {
+    let mut lints = Vec::new();
+    let graph = RelationGraph::new(model);
+
+    let mut references: Vec<Vec<bool>> = vec![Vec::new(); graph.nodes.len()];
+    graph.nodes.iter().enumerate().for_each(|(node, (t, r))| {
+        let mut found = Vec::new();
+        graph.collect_references(t, &r.access, false, &mut found);
+        found
+            .into_iter()
+            .filter(|(target, _)| *target != node)
+            .for_each(|(target, excluded)| references[target].push(excluded));
+    });
+    graph
+        .nodes
+        .iter()
+        .zip(references)
+        .for_each(|((t, r), excluded)| {
+            if excluded.is_empty() {
+                lints.push(ModelError::UnusedRelation {
+                    relation: (*r).clone(),
+                    target_type: (*t).clone(),
+                });
+            } else if excluded.iter().all(|e| *e) {
+                lints.push(ModelError::ExcludedOnlyRelation {
+                    relation: (*r).clone(),
+                    target_type: (*t).clone(),
+                });
+            }
+        });
+
+    // Every type is assignable in schema 1.0, so types without relations are fine
+    if !model.is_legacy_schema() {
+        let restricted: HashSet<&str> = model
+            .types
+            .iter()
+            .flat_map(|t| t.relations.iter())
+            .flat_map(|r| r.type_restrictions())
+            .map(|r| r.type_identifier.name.as_str())
+            .collect();
+        model
+            .types
+            .iter()
+            .filter(|t| t.relations.is_empty() && !restricted.contains(t.identifier.name.as_str()))
+            .for_each(|t| {
+                lints.push(ModelError::TypeWithoutRelations {
+                    target_type: t.clone(),
+                })
+            });
+    }
+
+    model.types.iter().for_each(|t| {
+        t.relations
+            .iter()
+            .for_each(|r| lint_access(&r.access, r, t, &mut lints))
+    });
+
+    // Cycles through untyped tuplesets are only assumed, as they can point to
+    // any type, so every `viewer from parent` of schema 1.0 would be one
+    find_relation_cycles(model)
+        .into_iter()
+        .filter(|cycle| !cycle.fatal && !through_untyped_tupleset(model, cycle))
+        .for_each(|cycle| lints.push(ModelError::GuardedCyclicRelation { cycle }));
+
+    lints
+}
+
+/// Returns true if a step of the cycle follows a tuple to userset whose
+/// tupleset has no type restrictions.
+fn through_untyped_tupleset(model: &AuthorizationModel, cycle: &RelationCycle) -> bool {
+    fn tupleset<'a>(access: &'a Access, reference: &Identifier) -> Option<&'a Identifier> {
+        match access {
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => (relation.span == reference.span).then_some(object),
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children.iter().find_map(|c| tupleset(c, reference))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => tupleset(base, reference).or_else(|| tupleset(subtract, reference)),
+            Access::Direct { .. } | Access::SelfComputed { .. } => None,
+        }
+    }
+
+    cycle.steps.iter().any(|step| {
+        tupleset(&step.relation.access, &step.reference).is_some_and(|tupleset| {
+            model
+                .types
+                .iter()
+                .filter(|t| t.identifier.name == step.type_name)
+                .flat_map(|t| t.relations.iter())
+                .find(|r| r.identifier.name == tupleset.name)
+                .is_none_or(|r| r.type_restrictions().is_empty())
+        })
+    })
+}
+
+/// Returns true if the relation has no direct assignment, meaning it is a
+/// permission computed from other relations.
+fn is_permission(access: &Access) -> bool {
+    match access {
+        Access::Direct { .. } => false,
+        Access::Computed { .. } | Access::SelfComputed { .. } => true,
+        Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+            children.iter().all(is_permission)
+        }
+        Access::Difference {
+            base,
+            subtract: _,
+            span: _,
+        } => is_permission(base),
     }
 }
 
+/// Checks type and relation names against the configured conventions.
+pub fn lint_naming(model: &AuthorizationModel, naming: &NamingConventions) -> Vec<ModelError> {
+    let mut lints = Vec::new();
+    model.types.iter().for_each(|t| {
+        let name = &t.identifier.name;
+        if let Some(convention) = naming.types {
+            if !convention.matches(name) {
+                lints.push(ModelError::TypeNamingConvention {
+                    target_type: t.clone(),
+                    convention,
+                    suggestion: convention.convert(name),
+                });
+            }
+        }
+        if naming.singular_types {
+            let singular = singularize(name);
+            if singular != *name {
+                lints.push(ModelError::PluralTypeName {
+                    target_type: t.clone(),
+                    suggestion: singular,
+                });
+            }
+        }
+        t.relations.iter().for_each(|r| {
+            let name = &r.identifier.name;
+            if let Some(convention) = naming.relations {
+                if !convention.matches(name) {
+                    lints.push(ModelError::RelationNamingConvention {
+                        relation: r.clone(),
+                        target_type: t.clone(),
+                        convention,
+                        suggestion: convention.convert(name),
+                    });
+                }
+            }
+            if let Some(prefix) = &naming.permission_prefix {
+                if is_permission(&r.access) && !name.starts_with(prefix.as_str()) {
+                    lints.push(ModelError::MissingPermissionPrefix {
+                        relation: r.clone(),
+                        target_type: t.clone(),
+                        prefix: prefix.clone(),
+                        suggestion: format!("{}{}", prefix, name),
+                    });
+                }
+            }
+        });
+    });
+    lints
+}
+
+/// Runs the checks and lints of the model with the rules of the config
+/// applied, returning every finding with the severity it should be reported
+/// with. Lints only run if none of the findings is an error.
+pub fn check_model_with_config(
+    model: &AuthorizationModel,
+    config: &Config,
+) -> Vec<(ModelError, Severity)> {
+    let configure = |errors: Vec<ModelError>| {
+        errors
+            .into_iter()
+            .filter_map(|e| config.severity(&e).map(|severity| (e, severity)))
+            .collect::<Vec<_>>()
+    };
+    // Relations added by extensions are checked as part of the type they
+    // extend
+    let mut resolved = model.clone();
+    resolved.resolve_extensions();
+    let model = &resolved;
+    let mut findings = configure(check_model(model).err().unwrap_or_default());
+    if !findings.iter().any(|(_, s)| *s == Severity::Error) {
+        findings.extend(configure(lint_model(model)));
+        findings.extend(configure(lint_naming(model, &config.naming)));
+    }
+    findings
+}
+
+/// Checks the model for errors, extensions are expected to be resolved
+/// already.
 pub fn check_model(model: &AuthorizationModel) -> Result<(), Vec<ModelError>> {
     let mut errors: Vec<ModelError> = Vec::new();
     let mut type_map: HashMap<String, &Type> = HashMap::new();
     model.types.iter().for_each(|t| {
-        // Check for duplicate type
-        if type_map.contains_key(&t.identifier.name) {
+        // Check for duplicate type, extensions are only left over if the type
+        // they extend is missing
+        if t.extension {
+            errors.push(ModelError::UnknownExtendedType {
+                target_type: t.clone(),
+            });
+        } else if type_map.contains_key(&t.identifier.name) {
             errors.push(ModelError::DuplicateTypeName {
-                type1: type_map.get(&t.identifier.name).unwrap().clone().clone(),
+                type1: (*type_map.get(&t.identifier.name).unwrap()).clone(),
                 type2: t.clone(),
             })
         }
@@ -148,11 +1901,7 @@ pub fn check_model(model: &AuthorizationModel) -> Result<(), Vec<ModelError>> {
             // Check for duplicate relation
             if relation_map.contains_key(&r.identifier.name) {
                 errors.push(ModelError::DuplicateRelationName {
-                    relation1: relation_map
-                        .get(&r.identifier.name)
-                        .unwrap()
-                        .clone()
-                        .clone(),
+                    relation1: (*relation_map.get(&r.identifier.name).unwrap()).clone(),
                     relation2: r.clone(),
                     target_type: t.clone(),
                 });
@@ -164,8 +1913,431 @@ pub fn check_model(model: &AuthorizationModel) -> Result<(), Vec<ModelError>> {
         });
     });
 
-    if errors.len() > 0 {
+    let mut condition_map: HashMap<String, &Condition> = HashMap::new();
+    model.conditions.iter().for_each(|c| {
+        if let Some(condition1) = condition_map.get(&c.identifier.name) {
+            errors.push(ModelError::DuplicateConditionName {
+                condition1: (*condition1).clone(),
+                condition2: c.clone(),
+            });
+        }
+        condition_map.insert(c.identifier.name.clone(), c);
+        check_condition(c, model, &mut errors);
+    });
+
+    find_relation_cycles(model)
+        .into_iter()
+        .filter(|cycle| cycle.fatal)
+        .for_each(|cycle| errors.push(ModelError::CyclicRelation { cycle }));
+
+    if !errors.is_empty() {
         return Err(errors);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openfga_common::SourceMap;
+    use openfga_model_dsl_parser::{parse_model, parse_modules};
+
+    fn check(src: &str) -> Vec<ModelError> {
+        let (model, _) = parse_model(src).unwrap();
+        check_model(&model).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn rejects_tuple_to_userset_with_unknown_tupleset() {
+        let errors = check(
+            "model
+  schema 1.0
+type user
+type app
+  relations
+    define owner as self
+    define app_manager as self or owner from owner and (a from a or b from b)
+",
+        );
+        let tuplesets = errors
+            .iter()
+            .map(|e| match e {
+                ModelError::UnknownTupleset {
+                    tupleset_identifier,
+                    relation,
+                    ..
+                } => {
+                    assert_eq!(relation.identifier.name, "app_manager");
+                    tupleset_identifier.name.as_str()
+                }
+                e => panic!("unexpected error {e}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tuplesets, vec!["a", "b"]);
+        assert!(errors.iter().all(|e| e.get_code() == 212));
+    }
+
+    #[test]
+    fn accepts_tuple_to_userset_with_defined_tupleset() {
+        let errors = check(
+            "model
+  schema 1.0
+type user
+type app
+  relations
+    define owner as self
+    define a as self
+    define b as self
+    define app_manager as self or owner from owner and (a from a or b from b)
+",
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn finds_symbols_in_the_file_of_the_offset() {
+        let mut sources = SourceMap::default();
+        sources.add_file(
+            "core.openfga",
+            "module core\ntype user\ntype doc\n  relations\n    define viewer: [user]\n",
+        );
+        let tracker = sources.add_file(
+            "tracker.openfga",
+            "module tracker\nextend type doc\n  relations\n    define editor: viewer\n",
+        );
+        let model = parse_modules("1.2", &sources).unwrap();
+        let offset = "module tracker\nextend type doc\n  relations\n    define editor: v".len();
+        let symbol = symbol_at_in_file(&model, tracker, offset).unwrap();
+        assert_eq!(symbol.identifier().name, "viewer");
+        let definitions = symbol_definitions(&model, &symbol);
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].span.as_ref().unwrap().file, 0);
+        // The same offset of the first file is in a type restriction
+        let symbol = symbol_at(&model, offset).unwrap();
+        assert_eq!(symbol.identifier().name, "user");
+    }
+
+    #[test]
+    fn renames_tuple_to_userset_relations_of_other_files() {
+        let mut sources = SourceMap::default();
+        sources.add_file(
+            "core.openfga",
+            "module core\ntype user\ntype folder\n  relations\n    define viewer: [user]\ntype team\n  relations\n    define viewer: [user]\n",
+        );
+        let tracker = sources.add_file(
+            "tracker.openfga",
+            "module tracker\ntype doc\n  relations\n    define parent: [folder, team]\n    define viewer: [user] or viewer from parent\n",
+        );
+        let model = parse_modules("1.2", &sources).unwrap();
+        let offset = "module core\ntype user\ntype folder\n  relations\n    define v".len();
+        let symbol = symbol_at(&model, offset).unwrap();
+        let mut renamed = rename_symbol(&model, &symbol, "reader")
+            .unwrap()
+            .into_iter()
+            .map(|i| {
+                let span = i.span.unwrap();
+                (span.file, span.start)
+            })
+            .collect::<Vec<_>>();
+        renamed.sort();
+        let core = "module core\ntype user\ntype folder\n  relations\n    define ".len();
+        let team = "module core\ntype user\ntype folder\n  relations\n    define viewer: [user]\ntype team\n  relations\n    define ".len();
+        let from = "module tracker\ntype doc\n  relations\n    define parent: [folder, team]\n    define viewer: [user] or ".len();
+        assert_eq!(renamed, vec![(0, core), (0, team), (tracker, from)]);
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("viewer", "viewer"), 0);
+        assert_eq!(edit_distance("viewer", "veiwer"), 2);
+        assert_eq!(edit_distance("viewer", "viewers"), 1);
+        assert_eq!(edit_distance("", "doc"), 3);
+        assert_eq!(edit_distance("häuser", "hauser"), 1);
+    }
+
+    #[test]
+    fn suggests_close_names_closest_first() {
+        let candidates = [
+            "can_viewers",
+            "can_edit",
+            "can_viewer",
+            "can_view",
+            "can_viewer",
+            "cn_vw",
+        ];
+        assert_eq!(
+            similar_names("can_viewr", candidates.into_iter()),
+            vec!["can_view", "can_viewer", "can_viewers"]
+        );
+        assert_eq!(
+            similar_names("viewr", ["viewers", "viewer"].into_iter()),
+            vec!["viewer"]
+        );
+        // Short names allow a single edit, longer ones one per three characters
+        assert_eq!(
+            similar_names("doc", ["dog", "dot", "cat"].into_iter()),
+            vec!["dog", "dot"]
+        );
+        assert!(similar_names("doc", ["dig"].into_iter()).is_empty());
+        assert_eq!(
+            similar_names("can_viewer", ["can_vie", "can_view"].into_iter()),
+            vec!["can_view", "can_vie"]
+        );
+        assert!(similar_names("viewer", ["viewer", "organization"].into_iter()).is_empty());
+    }
+
+    #[test]
+    fn suggests_names_for_unknown_relations_and_types() {
+        let errors = check(
+            "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [usr]
+    define can_view: veiwer
+",
+        );
+        let suggestions = errors
+            .iter()
+            .map(|e| (e.get_code(), e.get_similar_names().to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            suggestions,
+            vec![
+                (205, vec!["user".to_string()]),
+                (203, vec!["viewer".to_string()])
+            ]
+        );
+    }
+
+    fn cycles(src: &str) -> Vec<(String, Span)> {
+        check(src)
+            .into_iter()
+            .map(|e| match e {
+                ModelError::CyclicRelation { ref cycle } => (cycle.to_string(), e.get_span()),
+                e => panic!("unexpected error {e}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rejects_cycles_between_computed_relations() {
+        let src = "model
+  schema 1.1
+type user
+type doc
+  relations
+    define a: b
+    define b: a
+";
+        let cycles = cycles(src);
+        assert_eq!(cycles.len(), 1);
+        let (cycle, span) = &cycles[0];
+        assert_eq!(cycle, "doc#a -> doc#b -> doc#a");
+        let start = src.find("define a: b").unwrap() + "define a: ".len();
+        assert_eq!((span.start, span.end), (start, start + 1));
+    }
+
+    #[test]
+    fn rejects_cycles_through_tuple_to_usersets() {
+        let src = "model
+  schema 1.1
+type user
+type folder
+  relations
+    define parent: [folder]
+    define viewer: viewer from parent
+";
+        let cycles = cycles(src);
+        assert_eq!(cycles.len(), 1);
+        let (cycle, span) = &cycles[0];
+        assert_eq!(cycle, "folder#viewer -> folder#viewer");
+        let start = src.find("viewer from").unwrap();
+        assert_eq!((span.start, span.end), (start, start + "viewer".len()));
+    }
+
+    fn lint_codes(src: &str, config: &Config) -> Vec<u64> {
+        let (model, _) = parse_model(src).unwrap();
+        check_model_with_config(&model, config)
+            .iter()
+            .map(|(e, _)| e.get_code())
+            .collect()
+    }
+
+    #[test]
+    fn reports_unused_relations_only_if_configured() {
+        let src = "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [user]
+    define can_view: viewer
+";
+        assert!(!lint_codes(src, &Config::default()).contains(&301));
+        let config = Config::parse("rules:\n  301: warning\n").unwrap();
+        assert!(lint_codes(src, &config).contains(&301));
+    }
+
+    #[test]
+    fn reports_errors_with_the_configured_level() {
+        let src = "model
+  schema 1.1
+type user
+type user
+";
+        assert_eq!(lint_codes(src, &Config::default()), vec![201]);
+        let config = Config::parse("rules:\n  201: off\n").unwrap();
+        assert!(!lint_codes(src, &config).contains(&201));
+        let (model, _) = parse_model(src).unwrap();
+        let config = Config::parse("rules:\n  201: warning\n").unwrap();
+        let findings = check_model_with_config(&model, &config);
+        assert!(findings
+            .iter()
+            .any(|(e, s)| e.get_code() == 201 && *s == Severity::Warning));
+    }
+
+    fn naming_lints(src: &str, naming: &str) -> Vec<(u64, String)> {
+        let (model, _) = parse_model(src).unwrap();
+        let config = Config::parse(&format!("naming:\n{naming}")).unwrap();
+        lint_naming(&model, &config.naming)
+            .into_iter()
+            .map(|e| {
+                let suggestion = match &e {
+                    ModelError::TypeNamingConvention { suggestion, .. }
+                    | ModelError::RelationNamingConvention { suggestion, .. }
+                    | ModelError::PluralTypeName { suggestion, .. }
+                    | ModelError::MissingPermissionPrefix { suggestion, .. } => suggestion.clone(),
+                    e => panic!("unexpected lint {e}"),
+                };
+                (e.get_code(), suggestion)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_names_breaking_the_conventions() {
+        let src = "model
+  schema 1.1
+type user
+type DocumentFolders
+  relations
+    define ownerUser: [user]
+    define view: ownerUser
+    define can_edit: ownerUser and view
+";
+        assert!(naming_lints(src, "").is_empty());
+        assert_eq!(
+            naming_lints(src, "  types: snake_case\n  relations: snake_case\n"),
+            vec![
+                (307, "document_folders".to_string()),
+                (308, "owner_user".to_string())
+            ]
+        );
+        assert_eq!(
+            naming_lints(src, "  types: PascalCase\n  singular_types: true\n"),
+            vec![
+                (307, "User".to_string()),
+                (309, "DocumentFolder".to_string())
+            ]
+        );
+        assert_eq!(
+            naming_lints(src, "  relations: camelCase\n  permission_prefix: can_\n"),
+            vec![(310, "can_view".to_string()), (308, "canEdit".to_string())]
+        );
+    }
+
+    fn lints_of_relations(relations: &str) -> Vec<u64> {
+        let src = format!(
+            "model
+  schema 1.1
+type user
+type doc
+  relations
+    define owner: [user]
+    define blocked: [user]
+{relations}"
+        );
+        lint_codes(&src, &Config::default())
+    }
+
+    #[test]
+    fn reports_types_without_relations() {
+        let unused = "model
+  schema 1.1
+type user
+type tag
+type doc
+  relations
+    define owner: [user]
+";
+        assert_eq!(lint_codes(unused, &Config::default()), vec![302]);
+        let legacy = "model
+  schema 1.0
+type user
+type tag
+type doc
+  relations
+    define owner as self
+";
+        assert!(!lint_codes(legacy, &Config::default()).contains(&302));
+    }
+
+    #[test]
+    fn reports_relations_only_referenced_through_exclusions() {
+        assert_eq!(
+            lints_of_relations("    define viewer: owner but not blocked\n"),
+            vec![303]
+        );
+        assert!(lints_of_relations(
+            "    define viewer: owner but not blocked\n    define can_unblock: blocked\n"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_redundant_union_members() {
+        assert_eq!(
+            lints_of_relations("    define viewer: [user] or owner or owner or blocked\n"),
+            vec![304]
+        );
+        assert!(lints_of_relations("    define viewer: [user] or owner or blocked\n").is_empty());
+    }
+
+    #[test]
+    fn reports_unsatisfiable_access() {
+        assert_eq!(
+            lints_of_relations(
+                "    define viewer: owner but not owner\n    define editor: blocked and (owner but not blocked)\n"
+            ),
+            vec![305, 305]
+        );
+        assert!(lints_of_relations(
+            "    define viewer: owner but not blocked\n    define editor: blocked and (owner but not viewer)\n    define auditor: viewer\n"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_guarded_cycles_only_through_typed_tuplesets() {
+        let legacy = "model
+  schema 1.0
+type user
+type folder
+  relations
+    define parent as self
+    define viewer as self or viewer from parent
+";
+        assert!(!lint_codes(legacy, &Config::default()).contains(&306));
+        let typed = "model
+  schema 1.1
+type user
+type folder
+  relations
+    define parent: [folder]
+    define viewer: [user] or viewer from parent
+";
+        assert!(lint_codes(typed, &Config::default()).contains(&306));
+    }
+}
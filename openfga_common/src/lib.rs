@@ -1,6 +1,121 @@
 use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Range};
 
-pub type Span = std::ops::Range<usize>;
+/// Index of a source file in the files a model was loaded from, models parsed
+/// from a single source only use file 0.
+pub type FileId = usize;
+
+/// Character offsets of a node in the source file it was parsed from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, range: Range<usize>) -> Span {
+        Span {
+            file,
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Returns the offsets of the span, without its file.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+/// Source of a file a model was parsed from.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Path or uri the file is reported with
+    pub name: String,
+    pub src: String,
+    /// Character offsets the lines of the source start at
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, src: impl Into<String>) -> SourceFile {
+        let src = src.into();
+        let line_starts = std::iter::once(0)
+            .chain(
+                src.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        SourceFile {
+            name: name.into(),
+            src,
+            line_starts,
+        }
+    }
+
+    /// Returns the zero based line and column of the character offset,
+    /// columns count characters.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        (line, offset - self.line_starts[line])
+    }
+}
+
+/// Line and column the start of a span resolves to, both zero based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation<'a> {
+    pub name: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourceLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.name, self.line + 1, self.column + 1)
+    }
+}
+
+/// Files a model was parsed from, the index of a file is the file id of the
+/// spans pointing into it.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Adds the file to the map, returning its id.
+    pub fn add_file(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        self.files.push(SourceFile::new(name, src));
+        self.files.len() - 1
+    }
+
+    pub fn file(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// Resolves the start of the span to the file, line and column it points
+    /// to. Returns `None` if the file isn't part of the map.
+    pub fn resolve(&self, span: &Span) -> Option<SourceLocation<'_>> {
+        let file = self.file(span.file)?;
+        let (line, column) = file.line_column(span.start);
+        Some(SourceLocation {
+            name: &file.name,
+            line,
+            column,
+        })
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Identifier {
@@ -8,9 +123,112 @@ pub struct Identifier {
     pub span: Option<Span>,
 }
 
+/// `//` comment of the source, `text` includes the slashes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Option<Span>,
+}
+
+impl Comment {
+    /// Returns the text of the comment without the leading slashes.
+    pub fn content(&self) -> &str {
+        self.text.trim_start_matches('/').trim()
+    }
+}
+
+/// Comments attached to a node, `leading` ones are written on the lines above
+/// it and `trailing` ones behind it on its last line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Comments {
+    pub leading: Vec<Comment>,
+    pub trailing: Vec<Comment>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty()
+    }
+
+    /// Returns the leading comments joined into a single documentation text.
+    pub fn documentation(&self) -> Option<String> {
+        if self.leading.is_empty() {
+            return None;
+        }
+        Some(
+            self.leading
+                .iter()
+                .map(|c| c.content())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+/// User type allowed to be directly related to a relation, written as
+/// `user`, `group#member` or `user:*` inside the brackets of a direct access,
+/// optionally followed by `with condition_name`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TypeRestriction {
+    pub type_identifier: Identifier,
+    pub relation: Option<Identifier>,
+    pub wildcard: bool,
+    pub condition: Option<Identifier>,
+    pub span: Option<Span>,
+}
+
+/// Type of a condition parameter, `generic` holds the type argument of
+/// `list<T>` and `map<T>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ParameterType {
+    pub identifier: Identifier,
+    pub generic: Option<Box<ParameterType>>,
+    pub span: Option<Span>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConditionParameter {
+    pub identifier: Identifier,
+    pub parameter_type: ParameterType,
+    pub span: Option<Span>,
+}
+
+/// Named CEL expression which has to evaluate to true for a conditional
+/// relationship to be granted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub identifier: Identifier,
+    pub parameters: Vec<ConditionParameter>,
+    pub expression: String,
+    #[serde(default)]
+    pub comments: Comments,
+    pub span: Option<Span>,
+}
+
+impl Condition {
+    /// Returns the lines of the expression as written between the braces of
+    /// the condition. Every line is indented, keeping the indentation of
+    /// multiline expressions relative to their first line, empty lines stay
+    /// empty.
+    pub fn body_lines(&self) -> Vec<String> {
+        self.expression
+            .lines()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    String::new()
+                } else {
+                    format!("  {}", line.trim_end())
+                }
+            })
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Access {
+    /// Direct assignment, `self` if `restrictions` is empty
     Direct {
+        restrictions: Vec<TypeRestriction>,
         span: Option<Span>,
     },
     Computed {
@@ -41,13 +259,30 @@ pub enum Access {
 pub struct Relation {
     pub identifier: Identifier,
     pub access: Access,
+    #[serde(default)]
+    pub comments: Comments,
     pub span: Option<Span>,
 }
 
+impl Relation {
+    /// Returns the type restrictions of every direct access of the relation.
+    pub fn type_restrictions(&self) -> Vec<&TypeRestriction> {
+        let mut restrictions = Vec::new();
+        self.access.collect_type_restrictions(&mut restrictions);
+        restrictions
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Type {
     pub identifier: Identifier,
     pub relations: Vec<Relation>,
+    /// True for `extend type` definitions, which add relations to a type
+    /// defined in another module
+    #[serde(default)]
+    pub extension: bool,
+    #[serde(default)]
+    pub comments: Comments,
     pub span: Option<Span>,
 }
 
@@ -61,10 +296,75 @@ impl Type {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct AuthorizationModel {
+    /// Schema version declared in the `model` header, `None` for models
+    /// written in the 1.0 syntax without a header
+    pub schema_version: Option<String>,
+    /// Module declared by the `module` header of a file of a modular model,
+    /// which takes the place of the `model` header
+    #[serde(default)]
+    pub module: Option<Identifier>,
     pub types: Vec<Type>,
+    pub conditions: Vec<Condition>,
+    /// Comments above and behind the `model` header
+    #[serde(default)]
+    pub header_comments: Comments,
+    /// Comments after the last definition
+    #[serde(default)]
+    pub end_comments: Vec<Comment>,
 }
 
 impl AuthorizationModel {
+    /// Returns true if the model is written in the 1.0 syntax, using `as` and
+    /// `self` instead of colons and type restrictions.
+    pub fn is_legacy_schema(&self) -> bool {
+        self.module.is_none() && matches!(self.schema_version.as_deref(), None | Some("1.0"))
+    }
+
+    /// Merges the models parsed from the files of a modular model into one,
+    /// keeping the definitions in the order of the files. Extensions get
+    /// resolved, comments are dropped.
+    pub fn merge(schema_version: String, models: Vec<AuthorizationModel>) -> AuthorizationModel {
+        let mut merged = AuthorizationModel {
+            schema_version: Some(schema_version),
+            module: None,
+            types: Vec::new(),
+            conditions: Vec::new(),
+            header_comments: Comments::default(),
+            end_comments: Vec::new(),
+        };
+        for model in models {
+            merged.types.extend(model.types);
+            merged.conditions.extend(model.conditions);
+        }
+        merged.resolve_extensions();
+        merged
+    }
+
+    /// Moves the relations of every `extend type` definition to the end of
+    /// the type it extends. Extensions of types which are not defined are
+    /// kept.
+    pub fn resolve_extensions(&mut self) {
+        let (extensions, types): (Vec<Type>, Vec<Type>) =
+            self.types.drain(..).partition(|t| t.extension);
+        self.types = types;
+        for extension in extensions {
+            match self
+                .types
+                .iter_mut()
+                .find(|t| !t.extension && t.identifier.name == extension.identifier.name)
+            {
+                Some(extended) => extended.relations.extend(extension.relations),
+                None => self.types.push(extension),
+            }
+        }
+    }
+
+    pub fn condition_exists(&self, condition_name: &str) -> bool {
+        self.conditions
+            .iter()
+            .any(|c| c.identifier.name == condition_name)
+    }
+
     pub fn type_exists(&self, type_name: &str) -> bool {
         self.types.iter().any(|t| t.identifier.name == type_name)
     }
@@ -76,22 +376,325 @@ impl AuthorizationModel {
     }
 }
 
+impl fmt::Display for AuthorizationModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Type restrictions can't be written in the 1.0 syntax, so models
+        // using them are printed in the 1.1 syntax whatever their version
+        let restricted = self
+            .types
+            .iter()
+            .flat_map(|t| &t.relations)
+            .any(|r| !r.type_restrictions().is_empty());
+        let legacy = self.is_legacy_schema() && !restricted;
+        let schema_version = match &self.schema_version {
+            _ if self.is_legacy_schema() && restricted => Some("1.1"),
+            schema_version => schema_version.as_deref(),
+        };
+        if let Some(module) = &self.module {
+            writeln!(f, "module {}", module.name)?;
+        } else if let Some(schema_version) = schema_version {
+            writeln!(f, "model")?;
+            writeln!(f, "  schema {}", schema_version)?;
+        }
+        let header = self.module.is_some() || schema_version.is_some();
+        for (i, t) in self.types.iter().enumerate() {
+            if i > 0 || header {
+                writeln!(f)?;
+            }
+            t.fmt_dsl(f, legacy)?;
+        }
+        for condition in &self.conditions {
+            writeln!(f)?;
+            writeln!(f, "{}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "condition {}(", self.identifier.name)?;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{}: {}",
+                parameter.identifier.name, parameter.parameter_type
+            )?;
+        }
+        writeln!(f, ") {{")?;
+        for line in self.body_lines() {
+            writeln!(f, "{}", line)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for ParameterType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.identifier.name)?;
+        if let Some(generic) = &self.generic {
+            write!(f, "<{}>", generic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Type {
+    fn fmt_dsl(&self, f: &mut fmt::Formatter, legacy: bool) -> fmt::Result {
+        if self.extension {
+            write!(f, "extend ")?;
+        }
+        writeln!(f, "type {}", self.identifier.name)?;
+        if !legacy && self.relations.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "  relations")?;
+        for relation in &self.relations {
+            write!(f, "    ")?;
+            relation.fmt_dsl(f, legacy)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Relation {
+    fn fmt_dsl(&self, f: &mut fmt::Formatter, legacy: bool) -> fmt::Result {
+        if legacy {
+            write!(f, "define {} as {}", self.identifier.name, self.access)
+        } else {
+            write!(f, "define {}: {}", self.identifier.name, self.access)
+        }
+    }
+}
+
+impl Access {
+    fn collect_type_restrictions<'a>(&'a self, restrictions: &mut Vec<&'a TypeRestriction>) {
+        match self {
+            Access::Direct {
+                restrictions: direct,
+                span: _,
+            } => restrictions.extend(direct.iter()),
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children
+                    .iter()
+                    .for_each(|c| c.collect_type_restrictions(restrictions))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => {
+                base.collect_type_restrictions(restrictions);
+                subtract.collect_type_restrictions(restrictions);
+            }
+            Access::Computed { .. } | Access::SelfComputed { .. } => (),
+        }
+    }
+
+    /// Replaces the type restrictions of every direct access.
+    pub fn set_type_restrictions(&mut self, new_restrictions: &[TypeRestriction]) {
+        match self {
+            Access::Direct {
+                restrictions,
+                span: _,
+            } => *restrictions = new_restrictions.to_vec(),
+            Access::Union { children, span: _ } | Access::Intersection { children, span: _ } => {
+                children
+                    .iter_mut()
+                    .for_each(|c| c.set_type_restrictions(new_restrictions))
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => {
+                base.set_type_restrictions(new_restrictions);
+                subtract.set_type_restrictions(new_restrictions);
+            }
+            Access::Computed { .. } | Access::SelfComputed { .. } => (),
+        }
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        match self {
+            Access::Direct {
+                restrictions: _,
+                span,
+            }
+            | Access::Computed {
+                object: _,
+                relation: _,
+                span,
+            }
+            | Access::SelfComputed { relation: _, span }
+            | Access::Union { children: _, span }
+            | Access::Intersection { children: _, span }
+            | Access::Difference {
+                base: _,
+                subtract: _,
+                span,
+            } => span.clone(),
+        }
+    }
+
+    /// Returns true if the access has no operator, so it can be used as an
+    /// operand without being wrapped in parenthesis. Operators are never mixed
+    /// without parenthesis, as the OpenFGA tooling rejects that.
+    pub fn is_simple(&self) -> bool {
+        matches!(
+            self,
+            Access::Direct { .. } | Access::Computed { .. } | Access::SelfComputed { .. }
+        )
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter, parenthesize: bool) -> fmt::Result {
+        if parenthesize {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Access::Direct {
+                restrictions,
+                span: _,
+            } => {
+                if restrictions.is_empty() {
+                    return write!(f, "self");
+                }
+                write!(f, "[")?;
+                for (i, restriction) in restrictions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", restriction)?;
+                }
+                write!(f, "]")
+            }
+            Access::SelfComputed { relation, span: _ } => write!(f, "{}", relation.name),
+            Access::Computed {
+                object,
+                relation,
+                span: _,
+            } => write!(f, "{} from {}", relation.name, object.name),
+            Access::Union { children, span: _ } => {
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    child.fmt_operand(f, !child.is_simple())?;
+                }
+                Ok(())
+            }
+            Access::Intersection { children, span: _ } => {
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+                    child.fmt_operand(f, !child.is_simple())?;
+                }
+                Ok(())
+            }
+            Access::Difference {
+                base,
+                subtract,
+                span: _,
+            } => {
+                base.fmt_operand(f, !base.is_simple())?;
+                write!(f, " but not ")?;
+                subtract.fmt_operand(f, !subtract.is_simple())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TypeRestriction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.type_identifier.name)?;
+        if self.wildcard {
+            write!(f, ":*")?;
+        }
+        if let Some(relation) = &self.relation {
+            write!(f, "#{}", relation.name)?;
+        }
+        if let Some(condition) = &self.condition {
+            write!(f, " with {}", condition.name)?;
+        }
+        Ok(())
+    }
+}
+
 pub mod json {
     use serde::{Deserialize, Serialize};
     use std::collections::BTreeMap;
 
-    use crate::Access;
+    use crate::{
+        Access, Comments, ConditionParameter, Identifier, ParameterType, Relation, TypeRestriction,
+    };
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub struct AuthorizationModel {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub schema_version: Option<String>,
         pub type_definitions: Vec<Type>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub conditions: BTreeMap<String, Condition>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Condition {
+        pub name: String,
+        pub expression: String,
+        #[serde(default)]
+        pub parameters: BTreeMap<String, ConditionParamTypeRef>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct ConditionParamTypeRef {
+        pub type_name: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub generic_types: Vec<ConditionParamTypeRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub struct Type {
         #[serde(rename = "type")]
         pub type_name: String,
+        #[serde(default)]
         pub relations: BTreeMap<String, RelationData>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub metadata: Option<Metadata>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Metadata {
+        pub relations: BTreeMap<String, RelationMetadata>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct RelationMetadata {
+        #[serde(default)]
+        pub directly_related_user_types: Vec<RelationReference>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct RelationReference {
+        #[serde(rename = "type")]
+        pub type_name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub relation: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub wildcard: Option<BTreeMap<String, String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub condition: Option<String>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -138,16 +741,138 @@ pub mod json {
         },
     }
 
+    impl From<AuthorizationModel> for super::AuthorizationModel {
+        fn from(model: AuthorizationModel) -> Self {
+            super::AuthorizationModel {
+                schema_version: model.schema_version,
+                module: None,
+                types: model
+                    .type_definitions
+                    .into_iter()
+                    .map(|t| t.into())
+                    .collect(),
+                conditions: model.conditions.into_values().map(|c| c.into()).collect(),
+                header_comments: Comments::default(),
+                end_comments: Vec::new(),
+            }
+        }
+    }
+
+    impl From<Type> for super::Type {
+        fn from(type_in: Type) -> Self {
+            let mut metadata = type_in.metadata.map(|m| m.relations).unwrap_or_default();
+            let relations: Vec<Relation> = type_in
+                .relations
+                .into_iter()
+                .map(|(name, data)| {
+                    let mut access: Access = data.into();
+                    if let Some(relation_metadata) = metadata.remove(&name) {
+                        let restrictions: Vec<TypeRestriction> = relation_metadata
+                            .directly_related_user_types
+                            .into_iter()
+                            .map(|r| r.into())
+                            .collect();
+                        access.set_type_restrictions(&restrictions);
+                    }
+                    Relation {
+                        identifier: Identifier { name, span: None },
+                        access,
+                        comments: Comments::default(),
+                        span: None,
+                    }
+                })
+                .collect();
+            super::Type {
+                identifier: Identifier {
+                    name: type_in.type_name,
+                    span: None,
+                },
+                relations,
+                extension: false,
+                comments: Comments::default(),
+                span: None,
+            }
+        }
+    }
+
+    impl From<RelationData> for Access {
+        fn from(data: RelationData) -> Self {
+            match data {
+                RelationData::Direct { this: _ } => Access::Direct {
+                    restrictions: Vec::new(),
+                    span: None,
+                },
+                RelationData::Union { union } => Access::Union {
+                    children: union.child.into_iter().map(|d| d.into()).collect(),
+                    span: None,
+                },
+                RelationData::Intersection { intersection } => Access::Intersection {
+                    children: intersection.child.into_iter().map(|d| d.into()).collect(),
+                    span: None,
+                },
+                RelationData::Difference { base, subtract } => Access::Difference {
+                    base: Box::new((*base).into()),
+                    subtract: Box::new((*subtract).into()),
+                    span: None,
+                },
+                RelationData::ComputedUserset { computed_userset } => Access::SelfComputed {
+                    relation: Identifier {
+                        name: computed_userset.relation,
+                        span: None,
+                    },
+                    span: None,
+                },
+                RelationData::TupleToUserset { tuple_to_userset } => Access::Computed {
+                    object: Identifier {
+                        name: tuple_to_userset.tupleset.relation,
+                        span: None,
+                    },
+                    relation: Identifier {
+                        name: tuple_to_userset.computed_userset.relation,
+                        span: None,
+                    },
+                    span: None,
+                },
+            }
+        }
+    }
+
     impl From<super::AuthorizationModel> for AuthorizationModel {
-        fn from(model: super::AuthorizationModel) -> Self {
+        fn from(mut model: super::AuthorizationModel) -> Self {
+            model.resolve_extensions();
             AuthorizationModel {
+                schema_version: model.schema_version,
                 type_definitions: model.types.into_iter().map(|t| t.into()).collect(),
+                conditions: model
+                    .conditions
+                    .into_iter()
+                    .map(|c| (c.identifier.name.clone(), c.into()))
+                    .collect(),
             }
         }
     }
 
     impl From<super::Type> for Type {
         fn from(type_in: super::Type) -> Self {
+            let metadata: BTreeMap<String, RelationMetadata> = type_in
+                .relations
+                .iter()
+                .map(|relation| {
+                    (
+                        relation.identifier.name.clone(),
+                        RelationMetadata {
+                            directly_related_user_types: relation
+                                .type_restrictions()
+                                .into_iter()
+                                .map(|r| r.clone().into())
+                                .collect(),
+                        },
+                    )
+                })
+                .collect();
+            let has_restrictions = metadata
+                .values()
+                .any(|m| !m.directly_related_user_types.is_empty());
             let relations: BTreeMap<String, RelationData> = type_in
                 .relations
                 .into_iter()
@@ -156,6 +881,122 @@ pub mod json {
             Type {
                 type_name: type_in.identifier.name,
                 relations,
+                metadata: if has_restrictions {
+                    Some(Metadata {
+                        relations: metadata,
+                    })
+                } else {
+                    None
+                },
+            }
+        }
+    }
+
+    impl From<TypeRestriction> for RelationReference {
+        fn from(restriction: TypeRestriction) -> Self {
+            RelationReference {
+                type_name: restriction.type_identifier.name,
+                relation: restriction.relation.map(|r| r.name),
+                wildcard: if restriction.wildcard {
+                    Some(BTreeMap::new())
+                } else {
+                    None
+                },
+                condition: restriction.condition.map(|c| c.name),
+            }
+        }
+    }
+
+    impl From<RelationReference> for TypeRestriction {
+        fn from(reference: RelationReference) -> Self {
+            TypeRestriction {
+                type_identifier: Identifier {
+                    name: reference.type_name,
+                    span: None,
+                },
+                relation: reference
+                    .relation
+                    .map(|name| Identifier { name, span: None }),
+                wildcard: reference.wildcard.is_some(),
+                condition: reference
+                    .condition
+                    .filter(|name| !name.is_empty())
+                    .map(|name| Identifier { name, span: None }),
+                span: None,
+            }
+        }
+    }
+
+    impl From<super::Condition> for Condition {
+        fn from(condition: super::Condition) -> Self {
+            Condition {
+                name: condition.identifier.name,
+                expression: condition.expression,
+                parameters: condition
+                    .parameters
+                    .into_iter()
+                    .map(|p| (p.identifier.name, p.parameter_type.into()))
+                    .collect(),
+            }
+        }
+    }
+
+    impl From<Condition> for super::Condition {
+        fn from(condition: Condition) -> Self {
+            super::Condition {
+                identifier: Identifier {
+                    name: condition.name,
+                    span: None,
+                },
+                parameters: condition
+                    .parameters
+                    .into_iter()
+                    .map(|(name, type_ref)| ConditionParameter {
+                        identifier: Identifier { name, span: None },
+                        parameter_type: type_ref.into(),
+                        span: None,
+                    })
+                    .collect(),
+                expression: condition.expression,
+                comments: Comments::default(),
+                span: None,
+            }
+        }
+    }
+
+    const TYPE_NAME_PREFIX: &str = "TYPE_NAME_";
+
+    impl From<ParameterType> for ConditionParamTypeRef {
+        fn from(parameter_type: ParameterType) -> Self {
+            ConditionParamTypeRef {
+                type_name: format!(
+                    "{}{}",
+                    TYPE_NAME_PREFIX,
+                    parameter_type.identifier.name.to_uppercase()
+                ),
+                generic_types: parameter_type
+                    .generic
+                    .map(|g| vec![(*g).into()])
+                    .unwrap_or_default(),
+            }
+        }
+    }
+
+    impl From<ConditionParamTypeRef> for ParameterType {
+        fn from(type_ref: ConditionParamTypeRef) -> Self {
+            let name = type_ref
+                .type_name
+                .strip_prefix(TYPE_NAME_PREFIX)
+                .unwrap_or(&type_ref.type_name)
+                .to_lowercase();
+            ParameterType {
+                identifier: Identifier { name, span: None },
+                generic: type_ref
+                    .generic_types
+                    .into_iter()
+                    .next()
+                    .map(|g| Box::new(g.into())),
+                span: None,
             }
         }
     }
@@ -163,7 +1004,7 @@ pub mod json {
     impl From<Access> for RelationData {
         fn from(access: Access) -> Self {
             match access {
-                Access::Direct { span: _ } => RelationData::Direct {
+                Access::Direct { .. } => RelationData::Direct {
                     this: BTreeMap::new(),
                 },
                 Access::Union { children, span: _ } => RelationData::Union {
@@ -210,3 +1051,90 @@ pub mod json {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_offsets_to_lines_and_columns() {
+        let file = SourceFile::new("model.openfga", "model\n  schema 1.1\n\ntype user");
+        assert_eq!(file.line_column(0), (0, 0));
+        assert_eq!(file.line_column(5), (0, 5));
+        assert_eq!(file.line_column(6), (1, 0));
+        assert_eq!(file.line_column(15), (1, 9));
+        assert_eq!(file.line_column(19), (2, 0));
+        assert_eq!(file.line_column(20), (3, 0));
+        // The end of the file is right after its last character
+        assert_eq!(file.line_column(29), (3, 9));
+    }
+
+    #[test]
+    fn counts_characters_instead_of_bytes() {
+        let file = SourceFile::new("model.openfga", "// größe 🦀\ntype user\n");
+        assert_eq!(file.line_column("// größe 🦀".chars().count()), (0, 10));
+        assert_eq!(file.line_column(11), (1, 0));
+        assert_eq!(file.line_column(16), (1, 5));
+        assert_eq!(file.line_column(21), (2, 0));
+    }
+
+    #[test]
+    fn resolves_spans_in_the_file_they_point_to() {
+        let mut sources = SourceMap::default();
+        let core = sources.add_file("core.openfga", "module core\ntype user\n");
+        let tracker = sources.add_file("tracker.openfga", "module tracker\n\ntype doc\n");
+        assert_eq!((core, tracker), (0, 1));
+        assert_eq!(
+            sources.resolve(&Span::new(tracker, 21..24)),
+            Some(SourceLocation {
+                name: "tracker.openfga",
+                line: 2,
+                column: 5,
+            })
+        );
+        assert_eq!(
+            sources
+                .resolve(&Span::new(core, 17..21))
+                .unwrap()
+                .to_string(),
+            "core.openfga:2:6"
+        );
+        assert_eq!(sources.resolve(&Span::new(2, 0..1)), None);
+    }
+
+    fn relation(name: &str) -> Access {
+        Access::SelfComputed {
+            relation: Identifier {
+                name: name.to_string(),
+                span: None,
+            },
+            span: None,
+        }
+    }
+
+    #[test]
+    fn parenthesizes_nested_operators() {
+        let intersection = Access::Intersection {
+            children: vec![relation("b"), relation("c")],
+            span: None,
+        };
+        let difference = Access::Difference {
+            base: Box::new(relation("d")),
+            subtract: Box::new(relation("e")),
+            span: None,
+        };
+        let union = Access::Union {
+            children: vec![relation("a"), intersection.clone(), difference.clone()],
+            span: None,
+        };
+        assert_eq!(union.to_string(), "a or (b and c) or (d but not e)");
+        let intersection = Access::Intersection {
+            children: vec![union, difference],
+            span: None,
+        };
+        assert_eq!(
+            intersection.to_string(),
+            "(a or (b and c) or (d but not e)) and (d but not e)"
+        );
+    }
+}
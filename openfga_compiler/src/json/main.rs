@@ -1,10 +1,33 @@
-use openfga_common::json::AuthorizationModel;
-use std::{env, fs, path::Path};
+use clap::Parser as CliParser;
+use openfga_common::json::AuthorizationModel as JsonAuthModel;
+use openfga_common::AuthorizationModel;
+use std::{fs, path::PathBuf};
+
+#[derive(CliParser, Debug)]
+#[command(author, version, about = "Decompiles an OpenFGA json model into the dsl", long_about = None)]
+struct Args {
+    /// input path of json model file
+    input_file: PathBuf,
+
+    /// output path of dsl model, printed to stdout if omitted
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
 
 fn main() {
-    let path_string: String = env::args().nth(1).expect("Expected file argument");
-    let path = Path::new(&path_string);
-    let file = fs::File::open(&path).unwrap();
-    let data: AuthorizationModel = serde_json::from_reader(file).unwrap();
-    println!("{:?}", data);
+    let args = Args::parse();
+    let file = fs::File::open(&args.input_file).expect("Failed to read file");
+    let json_model: JsonAuthModel = match serde_json::from_reader(file) {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("Error deserializing json model: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let model: AuthorizationModel = json_model.into();
+    let dsl = model.to_string();
+    match args.output {
+        Some(output) => fs::write(output, dsl).expect("Write failed!"),
+        None => print!("{}", dsl),
+    }
 }
@@ -1,80 +1,373 @@
 use ariadne::{sources, Color, Fmt, Label, Report, ReportKind};
-use chumsky::{prelude::*, stream::Stream};
-use clap::Parser as CliParser;
-use openfga_checker::{check_model, ModelError};
+use chumsky::prelude::*;
+use clap::{Parser as CliParser, Subcommand};
+use openfga_checker::{check_model_with_config, Config, ModelError, Severity, PARAMETER_TYPES};
 use openfga_common::json::AuthorizationModel as JsonAuthModel;
-use openfga_common::AuthorizationModel;
-use openfga_model_dsl_parser::{better_parser, lexer, Token};
-use std::{fs, path::PathBuf};
+use openfga_common::{AuthorizationModel, SourceFile, SourceMap, Span};
+use openfga_diff::{diff_models, AccessEffect, Change};
+use openfga_formatter::format_model;
+use openfga_model_dsl_parser::{
+    parse_model, parse_modules, Manifest, ParseErrors, Token, MANIFEST_FILE_NAME,
+};
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+mod test_runner;
 
 #[derive(CliParser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    /// input path of dsl model file
-    input_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// input path of dsl model file, or of the fga.mod manifest of a modular
+    /// model
+    #[arg(required = true)]
+    input_file: Option<PathBuf>,
 
     /// output path of compiled model
-    #[arg(short, long)]
-    output: PathBuf,
+    #[arg(short, long, required = true)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Runs the assertions of a test file against a model
+    Test {
+        /// input path of dsl model file
+        input_file: PathBuf,
+
+        /// path of the yaml or json test file
+        test_file: PathBuf,
+    },
+    /// Formats dsl model files in place
+    Fmt {
+        /// paths of the dsl model files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// only check if the files are formatted, without writing them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Compares two versions of a model, exits with 1 on breaking changes
+    /// and with 2 if one of the models can't be read
+    Diff {
+        /// path of the old model, a dsl model file, fga.mod manifest or json
+        /// model
+        old: PathBuf,
+
+        /// path of the new model
+        new: PathBuf,
+
+        /// print the changes as json
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() {
     let args = Args::parse();
-    let src = fs::read_to_string(&args.input_file).expect("Failed to read file");
-    let path_string = args.input_file.into_os_string().into_string().unwrap();
-
-    let (tokens, errors) = lexer().parse_recovery_verbose(src.trim());
-    let len = src.chars().count();
-    println!("{:?} {:?}", tokens, errors);
-    let (types, errs) = better_parser()
-        .parse_recovery_verbose(Stream::from_iter(len..len + 1, tokens.unwrap().into_iter()));
-    println!("{:?} {:?}", types, errs);
-    match types {
-        Some(types) => {
-            let model = AuthorizationModel { types };
-            let res = check_model(&model);
-            match res {
-                Ok(()) => {
-                    let json_model: JsonAuthModel = model.into();
-                    let json = serde_json::to_string_pretty(&json_model);
-                    match json {
-                        Ok(string) => fs::write(&args.output, string).expect("Write failed!"),
-                        Err(err) => println!("Error serializing model to json: {}", err),
-                    }
-                }
-                Err(errors) => {
-                    errors.into_iter().for_each(|e| {
-                        let report = build_model_error_report(e, &path_string);
-
-                        report
-                            .finish()
-                            .print(sources(vec![(path_string.clone(), src.clone())]))
-                            .unwrap();
-                    });
-                }
+    let success = match args.command {
+        Some(Command::Test {
+            input_file,
+            test_file,
+        }) => test_runner::run(&input_file, &test_file),
+        // Every file is formatted, even if an earlier one failed
+        Some(Command::Fmt { files, check }) => {
+            files.iter().filter(|file| !format(file, check)).count() == 0
+        }
+        Some(Command::Diff { old, new, json }) => match diff(&old, &new, json) {
+            Some(compatible) => compatible,
+            None => process::exit(2),
+        },
+        None => compile(&args.input_file.unwrap(), &args.output.unwrap()),
+    };
+    if !success {
+        process::exit(1);
+    }
+}
+
+fn compile(input_file: &Path, output: &Path) -> bool {
+    let model = match load_model(input_file, true) {
+        Some((model, _)) => model,
+        None => return false,
+    };
+    let json_model: JsonAuthModel = model.into();
+    match serde_json::to_string_pretty(&json_model) {
+        Ok(string) => {
+            fs::write(output, string).expect("Write failed!");
+            true
+        }
+        Err(err) => {
+            println!("Error serializing model to json: {}", err);
+            false
+        }
+    }
+}
+
+/// Formats the model at the given path, returns false if it can't be read or
+/// parsed or if it isn't formatted in check mode.
+fn format(input_file: &Path, check: bool) -> bool {
+    let src = match read_model_file(input_file) {
+        Some(src) => src,
+        None => return false,
+    };
+    let source = SourceFile::new(input_file.display().to_string(), src);
+    let formatted = match format_model(&source.src) {
+        Ok(formatted) => formatted,
+        Err(errs) => {
+            print_parse_errors(errs, &source);
+            return false;
+        }
+    };
+    if formatted == source.src {
+        return true;
+    }
+    if check {
+        println!("{} is not formatted", source.name);
+        return false;
+    }
+    fs::write(input_file, formatted).expect("Write failed!");
+    true
+}
+
+/// Json output of the `diff` command.
+#[derive(Serialize)]
+struct DiffOutput<'a> {
+    breaking: bool,
+    changes: &'a [Change],
+}
+
+/// Prints the changes between the models at the given paths, returns false if
+/// any change is breaking and `None` if one of the models can't be read.
+fn diff(old: &Path, new: &Path, json: bool) -> Option<bool> {
+    let (old_model, new_model) = match (read_model(old), read_model(new)) {
+        (Some(old_model), Some(new_model)) => (old_model, new_model),
+        _ => return None,
+    };
+    let diff = diff_models(&old_model, &new_model);
+    if json {
+        let output = DiffOutput {
+            breaking: diff.is_breaking(),
+            changes: &diff.changes,
+        };
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return Some(!diff.is_breaking());
+    }
+    // Widened access isn't breaking, but grants new users and deserves a
+    // review before deploying
+    for change in &diff.changes {
+        let label = match change {
+            _ if change.is_breaking() => "breaking:".fg(Color::Red),
+            Change::AccessChanged {
+                effect: AccessEffect::Widened,
+                ..
+            } => "widened:".fg(Color::Yellow),
+            _ => "change:".fg(Color::Green),
+        };
+        println!("{} {}", label, change);
+    }
+    let breaking = diff.changes.iter().filter(|c| c.is_breaking()).count();
+    println!(
+        "diff result: {} changes; {} breaking",
+        diff.changes.len(),
+        breaking
+    );
+    Some(breaking == 0)
+}
+
+/// Reads a model without checking it, json files are read as compiled models
+/// and every other path like `load_model` does.
+fn read_model(input_file: &Path) -> Option<AuthorizationModel> {
+    if is_manifest(input_file) {
+        return parse_manifest(input_file).map(|(model, _)| model);
+    }
+    if input_file
+        .extension()
+        .is_none_or(|extension| extension != "json")
+    {
+        return parse_file(input_file).map(|(model, _)| model);
+    }
+    let src = read_model_file(input_file)?;
+    match serde_json::from_str::<JsonAuthModel>(&src) {
+        Ok(json_model) => Some(json_model.into()),
+        Err(err) => {
+            println!(
+                "Error deserializing json model {}: {}",
+                input_file.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Parses and checks the model at the given path, printing a report for every
+/// error found, with the rules of the applying `.openfgarc` config. Warnings
+/// and advice are only reported if `lints` is set. The path is either a model
+/// file or the manifest of a modular model. Returns the model along with the
+/// source map of its files, if it is valid.
+fn load_model(input_file: &Path, lints: bool) -> Option<(AuthorizationModel, SourceMap)> {
+    let (model, source_map) = if is_manifest(input_file) {
+        parse_manifest(input_file)?
+    } else {
+        parse_file(input_file)?
+    };
+    let config = match Config::load_for(input_file) {
+        Ok(config) => config,
+        Err(err) => {
+            println!("{}", err);
+            return None;
+        }
+    };
+    let findings = check_model_with_config(&model, &config);
+    let valid = !findings.iter().any(|(_, s)| *s == Severity::Error);
+    findings
+        .into_iter()
+        .filter(|(_, severity)| lints || *severity == Severity::Error)
+        .for_each(|(e, severity)| {
+            let report = build_model_error_report(e, severity, &source_map);
+
+            report.finish().print(report_sources(&source_map)).unwrap();
+        });
+    if valid {
+        Some((model, source_map))
+    } else {
+        None
+    }
+}
+
+fn is_manifest(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == MANIFEST_FILE_NAME)
+        || path.extension().is_some_and(|extension| extension == "mod")
+}
+
+/// Parses the model file at the given path, printing its parse errors.
+fn parse_file(input_file: &Path) -> Option<(AuthorizationModel, SourceMap)> {
+    let src = read_model_file(input_file)?;
+    let mut source_map = SourceMap::default();
+    let file = source_map.add_file(input_file.display().to_string(), src);
+    let source = source_map.file(file).unwrap();
+    match parse_model(&source.src) {
+        Ok((model, _)) => Some((model, source_map)),
+        Err(errs) => {
+            print_parse_errors(errs, source);
+            None
+        }
+    }
+}
+
+/// Reads the model file at the given path, printing why it can't be read.
+fn read_model_file(input_file: &Path) -> Option<String> {
+    match fs::read_to_string(input_file) {
+        Ok(src) => Some(src),
+        Err(err) => {
+            println!(
+                "Failed to read model file {}: {}",
+                input_file.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Parses the files listed by the manifest at the given path and merges them
+/// into one model, printing the parse errors of every file.
+fn parse_manifest(manifest_path: &Path) -> Option<(AuthorizationModel, SourceMap)> {
+    let manifest = match Manifest::load(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            println!("{}: {}", manifest_path.display(), err);
+            return None;
+        }
+    };
+    let mut source_map = SourceMap::default();
+    for path in manifest.files(manifest_path) {
+        match fs::read_to_string(&path) {
+            Ok(src) => {
+                source_map.add_file(path.display().to_string(), src);
+            }
+            Err(err) => {
+                println!("Failed to read module file {}: {}", path.display(), err);
+                return None;
             }
         }
-        None => {
-            errs.into_iter().for_each(|e| {
-                let report = build_simple_token_report(e, &path_string);
-
-                report
-                    .finish()
-                    .print(sources(vec![(path_string.clone(), src.clone())]))
-                    .unwrap();
+    }
+    match parse_modules(&manifest.schema, &source_map) {
+        Ok(model) => Some((model, source_map)),
+        Err(errors) => {
+            errors.into_iter().for_each(|(file, errs)| {
+                print_parse_errors(errs, source_map.file(file).unwrap());
             });
+            None
         }
     }
 }
 
+fn print_parse_errors(errs: ParseErrors, source: &SourceFile) {
+    match errs {
+        ParseErrors::Lexer(errs) => errs.into_iter().for_each(|e| {
+            let report = build_lexer_report(e, &source.name);
+
+            report
+                .finish()
+                .print(sources(vec![(source.name.clone(), source.src.clone())]))
+                .unwrap();
+        }),
+        ParseErrors::Parser(errs) => errs.into_iter().for_each(|e| {
+            let report = build_simple_token_report(e, &source.name);
+
+            report
+                .finish()
+                .print(sources(vec![(source.name.clone(), source.src.clone())]))
+                .unwrap();
+        }),
+    }
+}
+
+/// Returns the cache ariadne prints the reports of a model from.
+pub(crate) fn report_sources(source_map: &SourceMap) -> impl ariadne::Cache<String> {
+    sources(
+        source_map
+            .files()
+            .iter()
+            .map(|file| (file.name.clone(), file.src.clone()))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Builds the report of the error, pointing into the files of the source map.
 fn build_model_error_report(
     e: ModelError,
-    path_string: &String,
+    severity: Severity,
+    source_map: &SourceMap,
 ) -> ariadne::ReportBuilder<(String, std::ops::Range<usize>)> {
-    match e {
+    let kind = report_kind(severity);
+    let file = |span: &Span| {
+        source_map
+            .file(span.file)
+            .map_or_else(String::new, |file| file.name.clone())
+    };
+    let label = |span: Span| Label::new((file(&span), span.range()));
+    let similar = e.get_similar_names().to_vec();
+    let report = match e {
         ModelError::DuplicateTypeName { type1, type2 } => Report::build(
-            ReportKind::Error,
-            path_string.clone(),
+            kind,
+            file(&type1.span.clone().unwrap()),
             type1.span.clone().unwrap().start,
         )
         .with_code(201)
@@ -83,7 +376,7 @@ fn build_model_error_report(
             type1.identifier.name.clone().fg(Color::Blue)
         ))
         .with_label(
-            Label::new((path_string.clone(), type1.span.unwrap().clone()))
+            label(type1.span.unwrap().clone())
                 .with_message(format!(
                     "Type with name {} first was defined here",
                     type1.identifier.name.clone().fg(Color::Blue)
@@ -91,8 +384,8 @@ fn build_model_error_report(
                 .with_color(Color::Red),
         )
         .with_label(
-            Label::new((path_string.clone(), type2.span.unwrap().clone()))
-                .with_message(format!("and later here again!"))
+            label(type2.span.unwrap().clone())
+                .with_message("and later here again!".to_string())
                 .with_color(Color::Red),
         )
         .with_note("The names of type must be unique inside the model"),
@@ -102,8 +395,8 @@ fn build_model_error_report(
             relation2,
             target_type,
         } => Report::build(
-            ReportKind::Error,
-            path_string.clone(),
+            kind,
+            file(&target_type.span.clone().unwrap()),
             target_type.span.clone().unwrap().start,
         )
         .with_code(202)
@@ -113,7 +406,7 @@ fn build_model_error_report(
             target_type.identifier.name.clone().fg(Color::Blue)
         ))
         .with_label(
-            Label::new((path_string.clone(), relation1.span.unwrap().clone()))
+            label(relation1.span.unwrap().clone())
                 .with_message(format!(
                     "Relation {} first was defined here",
                     relation1.identifier.name.clone().fg(Color::Blue)
@@ -121,12 +414,12 @@ fn build_model_error_report(
                 .with_color(Color::Red),
         )
         .with_label(
-            Label::new((path_string.clone(), relation2.span.unwrap().clone()))
-                .with_message(format!("and again here"))
+            label(relation2.span.unwrap().clone())
+                .with_message("and again here".to_string())
                 .with_color(Color::Red),
         )
         .with_label(
-            Label::new((path_string.clone(), target_type.span.unwrap().clone()))
+            label(target_type.span.unwrap().clone())
                 .with_message(format!(
                     "on type {}",
                     target_type.identifier.name.clone().fg(Color::Blue)
@@ -140,9 +433,10 @@ fn build_model_error_report(
             access: _,
             relation,
             target_type,
+            suggestions: _,
         } => Report::build(
-            ReportKind::Error,
-            path_string.clone(),
+            kind,
+            file(&target_type.span.clone().unwrap()),
             target_type.span.clone().unwrap().start,
         )
         .with_code(203)
@@ -153,7 +447,7 @@ fn build_model_error_report(
             relation_name.name.clone().fg(Color::Green),
         ))
         .with_label(
-            Label::new((path_string.clone(), relation_name.span.unwrap().clone()))
+            label(relation_name.span.unwrap().clone())
                 .with_message(format!(
                     "Relation {} mentioned here does not exist on type {}",
                     relation_name.name.clone().fg(Color::Green),
@@ -169,8 +463,8 @@ fn build_model_error_report(
             relation,
             target_type,
         } => Report::build(
-            ReportKind::Error,
-            path_string.clone(),
+            kind,
+            file(&target_type.span.clone().unwrap()),
             target_type.span.clone().unwrap().start,
         )
         .with_code(204)
@@ -180,7 +474,7 @@ fn build_model_error_report(
             target_type.identifier.name.clone().fg(Color::Blue),
         ))
         .with_label(
-            Label::new((path_string.clone(), relation_name.span.unwrap().clone()))
+            label(relation_name.span.unwrap().clone())
                 .with_message(format!(
                     "Relation {} references itself here",
                     relation_name.name.clone().fg(Color::Green),
@@ -189,20 +483,637 @@ fn build_model_error_report(
         )
         .with_note("Relations can't reference themselves"),
 
-        _ => Report::build(ReportKind::Error, path_string.clone(), 0),
+        ModelError::UnknownRestrictionType {
+            restriction,
+            relation,
+            target_type,
+            suggestions: _,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(205)
+        .with_message(format!(
+            "Relation definition {} on type {} allows type {}, which does not exist",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            restriction.type_identifier.name.clone().fg(Color::Blue),
+        ))
+        .with_label(
+            label(restriction.type_identifier.span.unwrap().clone())
+            .with_message(format!(
+                "Type {} mentioned here does not exist",
+                restriction.type_identifier.name.clone().fg(Color::Blue),
+            ))
+            .with_color(Color::Red),
+        )
+        .with_note("Only types defined in the model can be directly related"),
+
+        ModelError::UnknownRestrictionRelation {
+            restriction,
+            relation,
+            target_type,
+            suggestions: _,
+        } => {
+            let restriction_relation = restriction.relation.clone().unwrap();
+            Report::build(
+                kind,
+                file(&target_type.span.clone().unwrap()),
+                target_type.span.clone().unwrap().start,
+            )
+            .with_code(206)
+            .with_message(format!(
+                "Relation definition {} on type {} allows {}, but relation {} does not exist on type {}",
+                relation.identifier.name.clone().fg(Color::Green),
+                target_type.identifier.name.clone().fg(Color::Blue),
+                restriction.to_string().fg(Color::Blue),
+                restriction_relation.name.clone().fg(Color::Green),
+                restriction.type_identifier.name.clone().fg(Color::Blue),
+            ))
+            .with_label(
+                label(restriction_relation.span.unwrap())
+                    .with_message(format!(
+                        "Relation {} mentioned here does not exist on type {}",
+                        restriction_relation.name.clone().fg(Color::Green),
+                        restriction.type_identifier.name.clone().fg(Color::Blue),
+                    ))
+                    .with_color(Color::Red),
+            )
+            .with_note("Usersets can only reference relations of their type")
+        }
+
+        ModelError::InvalidWildcardRestriction {
+            restriction,
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(207)
+        .with_message(format!(
+            "Relation definition {} on type {} allows wildcard {}, which can't reference a relation",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            restriction.to_string().fg(Color::Blue),
+        ))
+        .with_label(
+            label(restriction.span.unwrap().clone())
+                .with_message("Wildcard with relation used here".to_string())
+                .with_color(Color::Red),
+        )
+        .with_note(format!(
+            "Use either {} or {}",
+            format!("{}:*", restriction.type_identifier.name).fg(Color::Blue),
+            format!(
+                "{}#{}",
+                restriction.type_identifier.name,
+                restriction.relation.unwrap().name
+            )
+            .fg(Color::Blue),
+        )),
+
+        ModelError::UndefinedCondition {
+            condition_identifier,
+            restriction: _,
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(208)
+        .with_message(format!(
+            "Relation definition {} on type {} references condition {}, which is not defined",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            condition_identifier.name.clone().fg(Color::Yellow),
+        ))
+        .with_label(
+            label(condition_identifier.span.unwrap().clone())
+            .with_message(format!(
+                "Condition {} mentioned here is not defined",
+                condition_identifier.name.clone().fg(Color::Yellow),
+            ))
+            .with_color(Color::Red),
+        )
+        .with_note("Conditions need to be defined with a condition block"),
+
+        ModelError::UnusedCondition { condition } => Report::build(
+            kind,
+            file(&condition.span.clone().unwrap()),
+            condition.span.clone().unwrap().start,
+        )
+        .with_code(209)
+        .with_message(format!(
+            "Condition {} is defined but never used",
+            condition.identifier.name.clone().fg(Color::Yellow),
+        ))
+        .with_label(
+            label(condition.identifier.span.unwrap())
+                .with_message(format!(
+                    "Condition {} defined here",
+                    condition.identifier.name.clone().fg(Color::Yellow),
+                ))
+                .with_color(Color::Red),
+        )
+        .with_note("Reference the condition in a type restriction with `with` or remove it"),
+
+        ModelError::InvalidParameterType {
+            parameter_type,
+            parameter,
+            condition,
+        } => Report::build(
+            kind,
+            file(&condition.span.clone().unwrap()),
+            condition.span.clone().unwrap().start,
+        )
+        .with_code(210)
+        .with_message(format!(
+            "Parameter {} of condition {} has invalid type {}",
+            parameter.identifier.name.clone().fg(Color::Green),
+            condition.identifier.name.clone().fg(Color::Yellow),
+            parameter_type.to_string().fg(Color::Blue),
+        ))
+        .with_label(
+            label(parameter_type.span.clone().unwrap())
+                .with_message(format!(
+                    "Type {} is used here",
+                    parameter_type.to_string().fg(Color::Blue),
+                ))
+                .with_color(Color::Red),
+        )
+        .with_note(format!(
+            "Supported types are {}, list and map require a type argument like {}",
+            PARAMETER_TYPES
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", "),
+            "list<string>".fg(Color::Blue),
+        )),
+        ModelError::CyclicRelation { cycle } => {
+            let first = &cycle.steps[0];
+            cycle.steps.iter().fold(
+                Report::build(
+                    kind,
+                    file(&first.relation.span.clone().unwrap()),
+                    first.relation.span.clone().unwrap().start,
+                )
+                .with_code(211)
+                .with_message(format!(
+                    "Relations {} form a cycle which can never be resolved",
+                    cycle.to_string().fg(Color::Blue)
+                ))
+                .with_note(format!(
+                    "Add a direct assignment like {} to one of the relations to end the cycle",
+                    "[user]".fg(Color::Blue)
+                )),
+                |report, step| {
+                    report.with_label(
+                        label(step.reference.span.clone().unwrap())
+                            .with_message(format!(
+                                "{}#{} references {} here",
+                                step.type_name.clone().fg(Color::Yellow),
+                                step.relation.identifier.name.clone().fg(Color::Green),
+                                step.reference.name.clone().fg(Color::Green),
+                            ))
+                            .with_color(Color::Red),
+                    )
+                },
+            )
+        }
+        ModelError::UnknownTupleset {
+            tupleset_identifier,
+            access: _,
+            relation,
+            target_type,
+            suggestions: _,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(212)
+        .with_message(format!(
+            "Relation definition {} on type {} references tupleset {}, which does not exist",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            tupleset_identifier.name.clone().fg(Color::Green),
+        ))
+        .with_label(
+            label(tupleset_identifier.span.unwrap())
+                .with_message(format!(
+                    "Relation {} mentioned here does not exist on type {}",
+                    tupleset_identifier.name.clone().fg(Color::Green),
+                    target_type.identifier.name.clone().fg(Color::Blue)
+                ))
+                .with_color(Color::Red),
+        )
+        .with_note(format!(
+            "The relation after {} has to be defined on the same type",
+            "from".fg(Color::Blue)
+        )),
+        ModelError::UnknownTuplesetRelation {
+            relation_identifier,
+            tupleset,
+            candidate_types,
+            access: _,
+            relation,
+            target_type,
+            suggestions: _,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(213)
+        .with_message(format!(
+            "Relation definition {} on type {} references relation {} from {}, but none of the types assignable to {} define it",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            relation_identifier.name.clone().fg(Color::Green),
+            tupleset.identifier.name.clone().fg(Color::Green),
+            tupleset.identifier.name.clone().fg(Color::Green),
+        ))
+        .with_label(
+            label(relation_identifier.span.unwrap())
+                .with_message(format!(
+                    "Relation {} mentioned here is not defined on {}",
+                    relation_identifier.name.clone().fg(Color::Green),
+                    candidate_types.join(", ").fg(Color::Blue)
+                ))
+                .with_color(Color::Red),
+        )
+        .with_label(
+            label(tupleset.span.unwrap())
+                .with_message(format!(
+                    "Tupleset {} is defined here",
+                    tupleset.identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Yellow),
+        ),
+        ModelError::InvalidTupleset {
+            tupleset_identifier,
+            tupleset,
+            access: _,
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(214)
+        .with_message(format!(
+            "Relation definition {} on type {} uses {} as tupleset, but it is not a directly assignable relation without usersets",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            tupleset.identifier.name.clone().fg(Color::Green),
+        ))
+        .with_label(
+            label(tupleset_identifier.span.unwrap())
+                .with_message(format!(
+                    "Tupleset {} is used here",
+                    tupleset_identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Red),
+        )
+        .with_label(
+            label(tupleset.span.unwrap())
+                .with_message(format!(
+                    "Relation {} is defined here",
+                    tupleset.identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Yellow),
+        )
+        .with_note(format!(
+            "Tuplesets have to be defined like {} or {}",
+            "self".fg(Color::Blue),
+            "[type]".fg(Color::Blue)
+        )),
+        ModelError::DuplicateConditionName {
+            condition1,
+            condition2,
+        } => Report::build(
+            kind,
+            file(&condition1.span.clone().unwrap()),
+            condition1.span.clone().unwrap().start,
+        )
+        .with_code(215)
+        .with_message(format!(
+            "Condition with name {} got defined twice",
+            condition1.identifier.name.clone().fg(Color::Yellow)
+        ))
+        .with_label(
+            label(condition1.identifier.span.unwrap())
+                .with_message(format!(
+                    "Condition with name {} first was defined here",
+                    condition1.identifier.name.clone().fg(Color::Yellow)
+                ))
+                .with_color(Color::Red),
+        )
+        .with_label(
+            label(condition2.identifier.span.unwrap())
+                .with_message("and later here again!".to_string())
+                .with_color(Color::Red),
+        )
+        .with_note("The names of conditions must be unique inside the model"),
+        ModelError::UnknownExtendedType { target_type } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(216)
+        .with_message(format!(
+            "Type {} is extended, but it is not defined in any module",
+            target_type.identifier.name.clone().fg(Color::Blue)
+        ))
+        .with_label(
+            label(target_type.identifier.span.unwrap())
+                .with_message(format!(
+                    "Type {} is extended here",
+                    target_type.identifier.name.clone().fg(Color::Blue)
+                ))
+                .with_color(Color::Red),
+        )
+        .with_note("Only types defined by a module of the model can be extended"),
+        ModelError::UnusedRelation {
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&relation.span.clone().unwrap()),
+            relation.span.clone().unwrap().start,
+        )
+        .with_code(301)
+        .with_message(format!(
+            "Relation {} on type {} is never referenced",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+        ))
+        .with_label(
+            label(relation.identifier.span.unwrap())
+                .with_message(format!(
+                    "Relation {} is defined here",
+                    relation.identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Cyan),
+        )
+        .with_note("This is fine for relations which are only used in checks"),
+        ModelError::TypeWithoutRelations { target_type } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(302)
+        .with_message(format!(
+            "Type {} has no relations and is never used in a type restriction",
+            target_type.identifier.name.clone().fg(Color::Blue),
+        ))
+        .with_label(
+            label(target_type.identifier.span.unwrap())
+                .with_message(format!(
+                    "Type {} is defined here",
+                    target_type.identifier.name.clone().fg(Color::Blue)
+                ))
+                .with_color(Color::Yellow),
+        )
+        .with_note(format!(
+            "Allow the type on a relation like {} or remove it",
+            format!("[{}]", target_type.identifier.name).fg(Color::Blue)
+        )),
+        ModelError::ExcludedOnlyRelation {
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&relation.span.clone().unwrap()),
+            relation.span.clone().unwrap().start,
+        )
+        .with_code(303)
+        .with_message(format!(
+            "Relation {} on type {} is only referenced through {}",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            "but not".fg(Color::Blue),
+        ))
+        .with_label(
+            label(relation.identifier.span.unwrap())
+                .with_message(format!(
+                    "Relation {} is defined here",
+                    relation.identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Cyan),
+        ),
+        ModelError::RedundantUnionMember {
+            member,
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&relation.span.clone().unwrap()),
+            relation.span.clone().unwrap().start,
+        )
+        .with_code(304)
+        .with_message(format!(
+            "Relation definition {} on type {} contains {} more than once in a union",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            member.to_string().fg(Color::Blue),
+        ))
+        .with_label(
+            label(member.get_span().unwrap())
+                .with_message("This member is redundant")
+                .with_color(Color::Yellow),
+        ),
+        ModelError::UnsatisfiableAccess {
+            access,
+            relation,
+            target_type,
+        } => Report::build(
+            kind,
+            file(&relation.span.clone().unwrap()),
+            relation.span.clone().unwrap().start,
+        )
+        .with_code(305)
+        .with_message(format!(
+            "Relation definition {} on type {} contains {}, which can never be satisfied",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            access.to_string().fg(Color::Blue),
+        ))
+        .with_label(
+            label(access.get_span().unwrap())
+                .with_message("Nobody will ever be granted this")
+                .with_color(Color::Yellow),
+        ),
+        ModelError::GuardedCyclicRelation { cycle } => {
+            let first = &cycle.steps[0];
+            cycle.steps.iter().fold(
+                Report::build(
+                    kind,
+                    file(&first.relation.span.clone().unwrap()),
+                    first.relation.span.clone().unwrap().start,
+                )
+                .with_code(306)
+                .with_message(format!(
+                    "Relations {} form a cycle, which is resolved through a direct assignment",
+                    cycle.to_string().fg(Color::Blue)
+                )),
+                |report, step| {
+                    report.with_label(
+                        label(step.reference.span.clone().unwrap())
+                            .with_message(format!(
+                                "{}#{} references {} here",
+                                step.type_name.clone().fg(Color::Yellow),
+                                step.relation.identifier.name.clone().fg(Color::Green),
+                                step.reference.name.clone().fg(Color::Green),
+                            ))
+                            .with_color(Color::Cyan),
+                    )
+                },
+            )
+        }
+        ModelError::TypeNamingConvention {
+            target_type,
+            convention,
+            suggestion,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(307)
+        .with_message(format!(
+            "Type {} does not follow the {} naming convention",
+            target_type.identifier.name.clone().fg(Color::Blue),
+            convention,
+        ))
+        .with_label(
+            label(target_type.identifier.span.unwrap())
+                .with_message(format!("Type names have to be {}", convention))
+                .with_color(Color::Yellow),
+        )
+        .with_help(format!("Rename it to {}", suggestion.fg(Color::Blue))),
+        ModelError::RelationNamingConvention {
+            relation,
+            target_type,
+            convention,
+            suggestion,
+        } => Report::build(
+            kind,
+            file(&relation.span.clone().unwrap()),
+            relation.span.clone().unwrap().start,
+        )
+        .with_code(308)
+        .with_message(format!(
+            "Relation {} on type {} does not follow the {} naming convention",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            convention,
+        ))
+        .with_label(
+            label(relation.identifier.span.unwrap())
+                .with_message(format!("Relation names have to be {}", convention))
+                .with_color(Color::Yellow),
+        )
+        .with_help(format!("Rename it to {}", suggestion.fg(Color::Green))),
+        ModelError::PluralTypeName {
+            target_type,
+            suggestion,
+        } => Report::build(
+            kind,
+            file(&target_type.span.clone().unwrap()),
+            target_type.span.clone().unwrap().start,
+        )
+        .with_code(309)
+        .with_message(format!(
+            "Type {} should be named in singular form",
+            target_type.identifier.name.clone().fg(Color::Blue),
+        ))
+        .with_label(
+            label(target_type.identifier.span.unwrap())
+                .with_message("Type names have to be singular")
+                .with_color(Color::Yellow),
+        )
+        .with_help(format!("Rename it to {}", suggestion.fg(Color::Blue))),
+        ModelError::MissingPermissionPrefix {
+            relation,
+            target_type,
+            prefix,
+            suggestion,
+        } => Report::build(
+            kind,
+            file(&relation.span.clone().unwrap()),
+            relation.span.clone().unwrap().start,
+        )
+        .with_code(310)
+        .with_message(format!(
+            "Relation {} on type {} can't be assigned directly, so it should start with {}",
+            relation.identifier.name.clone().fg(Color::Green),
+            target_type.identifier.name.clone().fg(Color::Blue),
+            prefix.clone().fg(Color::Blue),
+        ))
+        .with_label(
+            label(relation.identifier.span.unwrap())
+                .with_message(format!(
+                    "Permission {} is defined here",
+                    relation.identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Yellow),
+        )
+        .with_help(format!("Rename it to {}", suggestion.fg(Color::Green))),
+    };
+    if similar.is_empty() {
+        return report;
+    }
+    let names: Vec<String> = similar
+        .into_iter()
+        .map(|name| name.fg(Color::Green).to_string())
+        .collect();
+    report.with_help(format!("Did you mean {}?", names.join(", ")))
+}
+
+fn report_kind(severity: Severity) -> ReportKind {
+    match severity {
+        Severity::Error => ReportKind::Error,
+        Severity::Warning => ReportKind::Warning,
+        Severity::Info => ReportKind::Advice,
     }
 }
 
+fn build_lexer_report(
+    e: Simple<char>,
+    path_string: &str,
+) -> ariadne::ReportBuilder<(String, std::ops::Range<usize>)> {
+    let found = match e.found() {
+        Some(c) => format!("character {}", c.fg(Color::Red)),
+        None => "end of input".to_string(),
+    };
+    Report::build(ReportKind::Error, path_string.to_string(), e.span().start)
+        .with_code(3)
+        .with_message(format!("Found unexpected {}", found))
+        .with_label(
+            Label::new((path_string.to_string(), e.span()))
+                .with_message(format!("Unexpected {}", found))
+                .with_color(Color::Red),
+        )
+}
+
 fn build_simple_token_report(
     e: Simple<Token>,
-    path_string: &String,
+    path_string: &str,
 ) -> ariadne::ReportBuilder<(String, std::ops::Range<usize>)> {
     let msg = get_simple_token_error_message(&e);
-    let report = Report::build(ReportKind::Error, path_string.clone(), e.span().start)
+    let report = Report::build(ReportKind::Error, path_string.to_string(), e.span().start)
         .with_code(3)
         .with_message(msg)
         .with_label(
-            Label::new((path_string.clone(), e.span()))
+            Label::new((path_string.to_string(), e.span()))
                 .with_message(match e.reason() {
                     chumsky::error::SimpleReason::Custom(msg) => msg.clone(),
                     _ => format!(
@@ -216,7 +1127,7 @@ fn build_simple_token_report(
         );
     let report = match e.reason() {
         chumsky::error::SimpleReason::Unclosed { span, delimiter } => report.with_label(
-            Label::new((path_string.clone(), span.clone()))
+            Label::new((path_string.to_string(), span.clone()))
                 .with_message(format!(
                     "Unclosed delimiter {}",
                     delimiter.fg(Color::Yellow)
@@ -236,14 +1147,14 @@ fn get_simple_token_error_message(e: &Simple<Token>) -> String {
         format!(
             "{}{}, expected instead {}",
             match e.found() {
-                Some(f) => format!("Found unexpected token {}", f.fg(Color::Blue).to_string()),
+                Some(f) => format!("Found unexpected token {}", f.fg(Color::Blue)),
                 None => format!(
                     "Found unexpected {}",
-                    String::from("end of input").fg(Color::Blue).to_string()
+                    String::from("end of input").fg(Color::Blue)
                 ),
             },
             if let Some(label) = e.label() {
-                format!(" while parsing {}", label.fg(Color::Green).to_string())
+                format!(" while parsing {}", label.fg(Color::Green))
             } else {
                 String::new()
             },
@@ -260,5 +1171,5 @@ fn get_simple_token_error_message(e: &Simple<Token>) -> String {
             },
         )
     };
-    return msg;
+    msg
 }
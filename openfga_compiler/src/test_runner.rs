@@ -0,0 +1,151 @@
+use ariadne::{Color, Fmt, Label, Report, ReportKind};
+use openfga_common::{AuthorizationModel, SourceMap};
+use openfga_evaluator::{check, split_object, Tuple, TupleStore};
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+use crate::{load_model, report_sources};
+
+#[derive(Deserialize, Debug)]
+pub struct TestFile {
+    #[serde(default)]
+    pub tuples: Vec<Tuple>,
+    #[serde(default)]
+    pub checks: Vec<CheckAssertion>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CheckAssertion {
+    pub user: String,
+    pub relation: String,
+    pub object: String,
+    pub allowed: bool,
+}
+
+/// Runs every check assertion of the test file against the model, returns
+/// true if all of them passed.
+pub fn run(input_file: &Path, test_file: &Path) -> bool {
+    // Only errors are reported, lints are left to compiling the model
+    let (model, source_map) = match load_model(input_file, false) {
+        Some(loaded) => loaded,
+        None => return false,
+    };
+
+    let test_src = match fs::read_to_string(test_file) {
+        Ok(test_src) => test_src,
+        Err(err) => {
+            println!("Failed to read test file {}: {}", test_file.display(), err);
+            return false;
+        }
+    };
+    // yaml is a superset of json, so both formats are handled by the yaml parser
+    let tests: TestFile = match serde_yaml::from_str(&test_src) {
+        Ok(tests) => tests,
+        Err(err) => {
+            println!("Error deserializing test file: {}", err);
+            return false;
+        }
+    };
+    let store: TupleStore = tests.tuples.into_iter().collect();
+
+    let mut failed = 0;
+    for assertion in &tests.checks {
+        let name = format!(
+            "check {} {} {}",
+            assertion.user, assertion.relation, assertion.object
+        );
+        let report = match check(
+            &model,
+            &store,
+            &assertion.user,
+            &assertion.relation,
+            &assertion.object,
+        ) {
+            Ok(allowed) if allowed == assertion.allowed => {
+                println!("test {} ... {}", name, "ok".fg(Color::Green));
+                continue;
+            }
+            Ok(allowed) => build_report(
+                &model,
+                assertion,
+                &source_map,
+                401,
+                format!(
+                    "Expected {} to be {} but it was {}",
+                    name.clone().fg(Color::Blue),
+                    describe(assertion.allowed),
+                    describe(allowed)
+                ),
+            ),
+            Err(err) => build_report(
+                &model,
+                assertion,
+                &source_map,
+                402,
+                format!(
+                    "Failed to evaluate {}: {}",
+                    name.clone().fg(Color::Blue),
+                    err
+                ),
+            ),
+        };
+        failed += 1;
+        println!("test {} ... {}", name, "FAILED".fg(Color::Red));
+        report.finish().print(report_sources(&source_map)).unwrap();
+    }
+
+    println!(
+        "test result: {} passed; {} failed",
+        tests.checks.len() - failed,
+        failed
+    );
+    failed == 0
+}
+
+fn describe(allowed: bool) -> &'static str {
+    if allowed {
+        "allowed"
+    } else {
+        "denied"
+    }
+}
+
+fn build_report(
+    model: &AuthorizationModel,
+    assertion: &CheckAssertion,
+    source_map: &SourceMap,
+    code: u32,
+    message: String,
+) -> ariadne::ReportBuilder<(String, std::ops::Range<usize>)> {
+    let relation = split_object(&assertion.object)
+        .ok()
+        .and_then(|(type_name, _)| {
+            model
+                .types
+                .iter()
+                .find(|t| t.identifier.name == type_name)
+                .and_then(|t| {
+                    t.relations
+                        .iter()
+                        .find(|r| r.identifier.name == assertion.relation)
+                })
+        });
+    let span = relation.and_then(|r| r.span.clone()).unwrap_or_default();
+    let path = source_map
+        .file(span.file)
+        .map_or_else(String::new, |file| file.name.clone());
+    let report = Report::build(ReportKind::Error, path.clone(), span.start)
+        .with_code(code)
+        .with_message(message);
+    match relation.and_then(|r| r.span.clone().map(|span| (r, span))) {
+        Some((relation, span)) => report.with_label(
+            Label::new((path, span.range()))
+                .with_message(format!(
+                    "Relation {} is defined here",
+                    relation.identifier.name.clone().fg(Color::Green)
+                ))
+                .with_color(Color::Red),
+        ),
+        None => report,
+    }
+}
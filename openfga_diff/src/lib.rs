@@ -0,0 +1,698 @@
+use openfga_common::{Access, AuthorizationModel, Condition, TypeRestriction};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Changes between two versions of a model, ordered by type, relation and
+/// condition name.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct ModelDiff {
+    pub changes: Vec<Change>,
+}
+
+impl ModelDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns true if any change can deny a check which the old model
+    /// allowed, or invalidate tuples written against it.
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(Change::is_breaking)
+    }
+}
+
+/// How the users granted by a relation changed, judged on the access
+/// expression of the relation alone. Relations it references report their own
+/// changes.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessEffect {
+    /// Everyone granted before still is, and more users can be granted
+    Widened,
+    /// No one is granted who wasn't before, but some users lose access
+    Narrowed,
+    /// The expression got rewritten, granting the same users
+    Equivalent,
+    /// Neither of the expressions is known to contain the other
+    Modified,
+}
+
+impl fmt::Display for AccessEffect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessEffect::Widened => write!(f, "widened"),
+            AccessEffect::Narrowed => write!(f, "narrowed"),
+            AccessEffect::Equivalent => write!(f, "equivalent"),
+            AccessEffect::Modified => write!(f, "modified"),
+        }
+    }
+}
+
+/// Single change of a model, access expressions and conditions are held in
+/// their normalized dsl form.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    SchemaVersionChanged {
+        old: String,
+        new: String,
+    },
+    TypeAdded {
+        type_name: String,
+    },
+    TypeRemoved {
+        type_name: String,
+    },
+    RelationAdded {
+        type_name: String,
+        relation: String,
+        access: String,
+    },
+    RelationRemoved {
+        type_name: String,
+        relation: String,
+        access: String,
+    },
+    AccessChanged {
+        type_name: String,
+        relation: String,
+        old: String,
+        new: String,
+        effect: AccessEffect,
+    },
+    ConditionAdded {
+        condition: String,
+    },
+    ConditionRemoved {
+        condition: String,
+    },
+    ConditionChanged {
+        condition: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl Change {
+    /// Returns true if the change can deny a check which the old model
+    /// allowed, or invalidate tuples written against it.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Change::TypeRemoved { type_name: _ }
+            | Change::RelationRemoved {
+                type_name: _,
+                relation: _,
+                access: _,
+            }
+            | Change::ConditionRemoved { condition: _ }
+            | Change::ConditionChanged {
+                condition: _,
+                old: _,
+                new: _,
+            } => true,
+            Change::AccessChanged {
+                type_name: _,
+                relation: _,
+                old: _,
+                new: _,
+                effect,
+            } => matches!(effect, AccessEffect::Narrowed | AccessEffect::Modified),
+            Change::SchemaVersionChanged { old: _, new: _ }
+            | Change::TypeAdded { type_name: _ }
+            | Change::RelationAdded {
+                type_name: _,
+                relation: _,
+                access: _,
+            }
+            | Change::ConditionAdded { condition: _ } => false,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::SchemaVersionChanged { old, new } => {
+                write!(f, "schema version changed from {} to {}", old, new)
+            }
+            Change::TypeAdded { type_name } => write!(f, "type {} was added", type_name),
+            Change::TypeRemoved { type_name } => write!(f, "type {} was removed", type_name),
+            Change::RelationAdded {
+                type_name,
+                relation,
+                access,
+            } => write!(
+                f,
+                "relation {} was added to type {}: {}",
+                relation, type_name, access
+            ),
+            Change::RelationRemoved {
+                type_name,
+                relation,
+                access,
+            } => write!(
+                f,
+                "relation {} was removed from type {}: {}",
+                relation, type_name, access
+            ),
+            Change::AccessChanged {
+                type_name,
+                relation,
+                old,
+                new,
+                effect,
+            } => write!(
+                f,
+                "relation {} on type {} was {}: {} -> {}",
+                relation, type_name, effect, old, new
+            ),
+            Change::ConditionAdded { condition } => {
+                write!(f, "condition {} was added", condition)
+            }
+            Change::ConditionRemoved { condition } => {
+                write!(f, "condition {} was removed", condition)
+            }
+            Change::ConditionChanged {
+                condition,
+                old,
+                new,
+            } => write!(f, "condition {} was changed: {} -> {}", condition, old, new),
+        }
+    }
+}
+
+/// Compares two versions of a model. Spans, comments and the order of
+/// definitions, operands and type restrictions are ignored, relations added
+/// by `extend type` count as relations of the extended type.
+pub fn diff_models(old: &AuthorizationModel, new: &AuthorizationModel) -> ModelDiff {
+    let mut changes = Vec::new();
+
+    let old_version = schema_version(old);
+    let new_version = schema_version(new);
+    if old_version != new_version {
+        changes.push(Change::SchemaVersionChanged {
+            old: old_version,
+            new: new_version,
+        });
+    }
+
+    let old_types = relations_by_type(old);
+    let new_types = relations_by_type(new);
+    let no_relations = BTreeMap::new();
+    for type_name in names(&old_types, &new_types) {
+        match (old_types.get(type_name), new_types.get(type_name)) {
+            (Some(_), None) => {
+                // The relations of removed types are not listed one by one
+                changes.push(Change::TypeRemoved {
+                    type_name: type_name.to_string(),
+                });
+                continue;
+            }
+            (None, Some(_)) => changes.push(Change::TypeAdded {
+                type_name: type_name.to_string(),
+            }),
+            _ => (),
+        }
+        let old_relations = old_types.get(type_name).unwrap_or(&no_relations);
+        let new_relations = new_types.get(type_name).unwrap_or(&no_relations);
+        for relation in names(old_relations, new_relations) {
+            let change = match (old_relations.get(relation), new_relations.get(relation)) {
+                (Some(old_access), Some(new_access)) => {
+                    let old_text = old_access.to_string();
+                    let new_text = new_access.to_string();
+                    if old_text == new_text {
+                        continue;
+                    }
+                    Change::AccessChanged {
+                        type_name: type_name.to_string(),
+                        relation: relation.to_string(),
+                        old: old_text,
+                        new: new_text,
+                        effect: access_effect(old_access, new_access),
+                    }
+                }
+                (Some(old_access), None) => Change::RelationRemoved {
+                    type_name: type_name.to_string(),
+                    relation: relation.to_string(),
+                    access: old_access.to_string(),
+                },
+                (None, Some(new_access)) => Change::RelationAdded {
+                    type_name: type_name.to_string(),
+                    relation: relation.to_string(),
+                    access: new_access.to_string(),
+                },
+                (None, None) => unreachable!("relation name without a relation"),
+            };
+            changes.push(change);
+        }
+    }
+
+    let old_conditions = conditions_by_name(old);
+    let new_conditions = conditions_by_name(new);
+    for condition in names(&old_conditions, &new_conditions) {
+        let change = match (old_conditions.get(condition), new_conditions.get(condition)) {
+            (Some(old_signature), Some(new_signature)) if old_signature != new_signature => {
+                Change::ConditionChanged {
+                    condition: condition.to_string(),
+                    old: old_signature.clone(),
+                    new: new_signature.clone(),
+                }
+            }
+            (Some(_), Some(_)) => continue,
+            (Some(_), None) => Change::ConditionRemoved {
+                condition: condition.to_string(),
+            },
+            (None, Some(_)) => Change::ConditionAdded {
+                condition: condition.to_string(),
+            },
+            (None, None) => unreachable!("condition name without a condition"),
+        };
+        changes.push(change);
+    }
+
+    ModelDiff { changes }
+}
+
+/// Returns the names defined by either of the versions, sorted.
+fn names<'a, V>(old: &BTreeMap<&'a str, V>, new: &BTreeMap<&'a str, V>) -> BTreeSet<&'a str> {
+    old.keys().chain(new.keys()).copied().collect()
+}
+
+/// Models without a `model` header are written in the 1.0 syntax.
+fn schema_version(model: &AuthorizationModel) -> String {
+    model
+        .schema_version
+        .clone()
+        .unwrap_or_else(|| String::from("1.0"))
+}
+
+/// Returns the normalized access of every relation by type and relation name.
+fn relations_by_type(model: &AuthorizationModel) -> BTreeMap<&str, BTreeMap<&str, Access>> {
+    let mut types: BTreeMap<&str, BTreeMap<&str, Access>> = BTreeMap::new();
+    for t in &model.types {
+        let relations = types.entry(&t.identifier.name).or_default();
+        for relation in &t.relations {
+            relations.insert(&relation.identifier.name, normalize(&relation.access));
+        }
+    }
+    types
+}
+
+/// Returns the signature and expression of every condition by name, with the
+/// parameters sorted and the whitespace of the expression collapsed.
+fn conditions_by_name(model: &AuthorizationModel) -> BTreeMap<&str, String> {
+    model
+        .conditions
+        .iter()
+        .map(|c| (c.identifier.name.as_str(), condition_signature(c)))
+        .collect()
+}
+
+fn condition_signature(condition: &Condition) -> String {
+    let mut parameters: Vec<String> = condition
+        .parameters
+        .iter()
+        .map(|p| format!("{}: {}", p.identifier.name, p.parameter_type))
+        .collect();
+    parameters.sort();
+    let expression: Vec<&str> = condition.expression.split_whitespace().collect();
+    format!(
+        "{}({}) {{ {} }}",
+        condition.identifier.name,
+        parameters.join(", "),
+        expression.join(" ")
+    )
+}
+
+/// Brings the access into a canonical form: nested unions and intersections
+/// are flattened and their operands, like the type restrictions of a direct
+/// access, are sorted and deduplicated by their dsl form.
+fn normalize(access: &Access) -> Access {
+    match access {
+        Access::Direct {
+            restrictions,
+            span: _,
+        } => {
+            let mut restrictions = restrictions.clone();
+            restrictions.sort_by_key(|r| r.to_string());
+            restrictions.dedup_by_key(|r| r.to_string());
+            Access::Direct {
+                restrictions,
+                span: None,
+            }
+        }
+        Access::Union { children, span: _ } => {
+            let children = normalize_operands(children, |a| match a {
+                Access::Union { children, span: _ } => Some(children),
+                _ => None,
+            });
+            match <[Access; 1]>::try_from(children) {
+                Ok([child]) => child,
+                Err(children) => Access::Union {
+                    children,
+                    span: None,
+                },
+            }
+        }
+        Access::Intersection { children, span: _ } => {
+            let children = normalize_operands(children, |a| match a {
+                Access::Intersection { children, span: _ } => Some(children),
+                _ => None,
+            });
+            match <[Access; 1]>::try_from(children) {
+                Ok([child]) => child,
+                Err(children) => Access::Intersection {
+                    children,
+                    span: None,
+                },
+            }
+        }
+        Access::Difference {
+            base,
+            subtract,
+            span: _,
+        } => Access::Difference {
+            base: Box::new(normalize(base)),
+            subtract: Box::new(normalize(subtract)),
+            span: None,
+        },
+        Access::Computed {
+            object,
+            relation,
+            span: _,
+        } => Access::Computed {
+            object: object.clone(),
+            relation: relation.clone(),
+            span: None,
+        },
+        Access::SelfComputed { relation, span: _ } => Access::SelfComputed {
+            relation: relation.clone(),
+            span: None,
+        },
+    }
+}
+
+/// Normalizes the operands, inlining the ones which `nested` returns the
+/// operands of.
+fn normalize_operands(
+    children: &[Access],
+    nested: impl Fn(&Access) -> Option<&Vec<Access>>,
+) -> Vec<Access> {
+    let mut operands = Vec::new();
+    for child in children.iter().map(normalize) {
+        match nested(&child) {
+            Some(grandchildren) => operands.extend(grandchildren.iter().cloned()),
+            None => operands.push(child),
+        }
+    }
+    operands.sort_by_key(|a| a.to_string());
+    operands.dedup_by_key(|a| a.to_string());
+    operands
+}
+
+fn access_effect(old: &Access, new: &Access) -> AccessEffect {
+    match (is_subset(old, new), is_subset(new, old)) {
+        (true, true) => AccessEffect::Equivalent,
+        (true, false) => AccessEffect::Widened,
+        (false, true) => AccessEffect::Narrowed,
+        (false, false) => AccessEffect::Modified,
+    }
+}
+
+/// Returns true if every user granted by `a` is known to be granted by `b`.
+/// Both have to be normalized. The check is conservative, it may miss that
+/// an access contains another one, but never claims it wrongly.
+fn is_subset(a: &Access, b: &Access) -> bool {
+    if a.to_string() == b.to_string() {
+        return true;
+    }
+    match (a, b) {
+        (Access::Union { children, span: _ }, _) => children.iter().all(|c| is_subset(c, b)),
+        (_, Access::Intersection { children, span: _ }) => children.iter().all(|c| is_subset(a, c)),
+        (_, Access::Union { children, span: _ }) if children.iter().any(|c| is_subset(a, c)) => {
+            true
+        }
+        (Access::Intersection { children, span: _ }, _)
+            if children.iter().any(|c| is_subset(c, b)) =>
+        {
+            true
+        }
+        (
+            Access::Direct {
+                restrictions: a_restrictions,
+                span: _,
+            },
+            Access::Direct {
+                restrictions: b_restrictions,
+                span: _,
+            },
+        ) => {
+            // A `self` of the 1.0 syntax only equals itself
+            !a_restrictions.is_empty()
+                && !b_restrictions.is_empty()
+                && a_restrictions
+                    .iter()
+                    .all(|r| b_restrictions.iter().any(|s| is_restriction_subset(r, s)))
+        }
+        (
+            Access::Difference {
+                base: a_base,
+                subtract: a_subtract,
+                span: _,
+            },
+            Access::Difference {
+                base: b_base,
+                subtract: b_subtract,
+                span: _,
+            },
+        ) if is_subset(a_base, b_base) && is_subset(b_subtract, a_subtract) => true,
+        (
+            Access::Difference {
+                base,
+                subtract: _,
+                span: _,
+            },
+            _,
+        ) => is_subset(base, b),
+        _ => false,
+    }
+}
+
+/// A restriction with a condition grants a subset of the same restriction
+/// without one.
+fn is_restriction_subset(a: &TypeRestriction, b: &TypeRestriction) -> bool {
+    a.to_string() == b.to_string()
+        || (b.condition.is_none()
+            && a.type_identifier.name == b.type_identifier.name
+            && a.relation.as_ref().map(|r| &r.name) == b.relation.as_ref().map(|r| &r.name)
+            && a.wildcard == b.wildcard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openfga_model_dsl_parser::parse_model;
+
+    const MODEL: &str = "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user]
+type doc
+  relations
+    define owner: [user]
+    define blocked: [user]
+    define editor: [user, group#member] or owner
+    define viewer: [user] or editor
+condition in_office(ip: ipaddress, office: ipaddress) {
+  ip == office
+}
+";
+
+    fn parse(src: &str) -> AuthorizationModel {
+        parse_model(src).unwrap().0
+    }
+
+    /// Diffs the model against itself with the definition of the relation
+    /// replaced, returns the single change expected.
+    fn change_relation(line: &str, access: &str) -> Change {
+        let old = parse(MODEL);
+        let prefix = format!("define {}: ", line);
+        let src: String = MODEL
+            .lines()
+            .map(|l| match l.trim_start().strip_prefix(&prefix) {
+                Some(_) => format!("    {}{}\n", prefix, access),
+                None => format!("{}\n", l),
+            })
+            .collect();
+        let diff = diff_models(&old, &parse(&src));
+        assert_eq!(diff.changes.len(), 1, "{:?}", diff.changes);
+        diff.changes[0].clone()
+    }
+
+    fn effect(change: &Change) -> AccessEffect {
+        match change {
+            Change::AccessChanged { effect, .. } => *effect,
+            change => panic!("unexpected change {}", change),
+        }
+    }
+
+    #[test]
+    fn finds_no_changes_in_the_same_model() {
+        assert!(diff_models(&parse(MODEL), &parse(MODEL)).is_empty());
+    }
+
+    #[test]
+    fn ignores_the_order_and_nesting_of_operands() {
+        let diff = diff_models(
+            &parse(MODEL),
+            &parse(&MODEL.replace(
+                "define editor: [user, group#member] or owner",
+                "define editor: owner or ([group#member, user] or owner)",
+            )),
+        );
+        assert!(diff.is_empty(), "{:?}", diff.changes);
+    }
+
+    #[test]
+    fn flattens_nested_unions_and_intersections() {
+        let access = |src: &str| {
+            let model = parse(&format!(
+                "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define a: [user]\n    define b: [user]\n    define c: [user]\n    define d: {}\n",
+                src
+            ));
+            normalize(&model.types[1].relations[3].access).to_string()
+        };
+        assert_eq!(access("c or (b or a)"), access("a or b or c"));
+        assert_eq!(access("(c and a) and b"), access("a and b and c"));
+        assert_eq!(access("a or a"), "a");
+        assert_ne!(access("a or (b and c)"), access("a or b or c"));
+    }
+
+    #[test]
+    fn classifies_widened_access() {
+        let change = change_relation("viewer", "[user, user:*] or editor");
+        assert_eq!(effect(&change), AccessEffect::Widened);
+        assert!(!change.is_breaking());
+        let change = change_relation("viewer", "[user] or editor or owner");
+        assert_eq!(effect(&change), AccessEffect::Widened);
+    }
+
+    #[test]
+    fn classifies_narrowed_access() {
+        let change = change_relation("viewer", "editor");
+        assert_eq!(effect(&change), AccessEffect::Narrowed);
+        assert!(change.is_breaking());
+        let change = change_relation("viewer", "[user] and editor");
+        assert_eq!(effect(&change), AccessEffect::Narrowed);
+    }
+
+    #[test]
+    fn classifies_equivalent_access() {
+        let change = change_relation("viewer", "[user] or editor or (editor and owner)");
+        assert_eq!(effect(&change), AccessEffect::Equivalent);
+        assert!(!change.is_breaking());
+    }
+
+    #[test]
+    fn classifies_modified_access() {
+        let change = change_relation("viewer", "[user] or owner");
+        assert_eq!(effect(&change), AccessEffect::Modified);
+        assert!(change.is_breaking());
+    }
+
+    #[test]
+    fn classifies_conditions_on_type_restrictions() {
+        let change = change_relation("owner", "[user with in_office]");
+        assert_eq!(effect(&change), AccessEffect::Narrowed);
+        let change = change_relation("owner", "[user, user with in_office]");
+        assert_eq!(effect(&change), AccessEffect::Equivalent);
+        let conditional = MODEL.replace(
+            "define owner: [user]",
+            "define owner: [user with in_office]",
+        );
+        let diff = diff_models(&parse(&conditional), &parse(MODEL));
+        assert_eq!(effect(&diff.changes[0]), AccessEffect::Widened);
+    }
+
+    #[test]
+    fn classifies_differences() {
+        let change = change_relation("viewer", "([user] or editor) but not blocked");
+        assert_eq!(effect(&change), AccessEffect::Narrowed);
+        let old = MODEL.replace(
+            "define viewer: [user] or editor",
+            "define viewer: [user] but not blocked",
+        );
+        let widened = old.replace("[user] but not", "([user] or editor) but not");
+        let diff = diff_models(&parse(&old), &parse(&widened));
+        assert_eq!(effect(&diff.changes[0]), AccessEffect::Widened);
+        let narrowed = old.replace("but not blocked", "but not (blocked or owner)");
+        let diff = diff_models(&parse(&old), &parse(&narrowed));
+        assert_eq!(effect(&diff.changes[0]), AccessEffect::Narrowed);
+    }
+
+    #[test]
+    fn lists_added_and_removed_definitions() {
+        let new = MODEL
+            .replace("type group\n  relations\n    define member: [user]\n", "")
+            .replace(
+                "define editor: [user, group#member] or owner",
+                "define editor: [user] or owner",
+            )
+            .replace(
+                "    define blocked: [user]\n",
+                "    define commenter: [user]\n",
+            )
+            .replace("condition in_office", "condition at_office");
+        let diff = diff_models(&parse(MODEL), &parse(&new));
+        let changes: Vec<String> = diff.changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "relation blocked was removed from type doc: [user]",
+                "relation commenter was added to type doc: [user]",
+                "relation editor on type doc was narrowed: [group#member, user] or owner -> [user] or owner",
+                "type group was removed",
+                "condition at_office was added",
+                "condition in_office was removed",
+            ]
+        );
+        assert!(diff.is_breaking());
+    }
+
+    #[test]
+    fn compares_conditions_by_signature() {
+        let reformatted = MODEL.replace(
+            "in_office(ip: ipaddress, office: ipaddress) {\n  ip == office\n}",
+            "in_office(office: ipaddress, ip: ipaddress) {\n  ip ==\n    office\n}",
+        );
+        assert!(diff_models(&parse(MODEL), &parse(&reformatted)).is_empty());
+        let changed = MODEL.replace("ip == office", "ip != office");
+        let diff = diff_models(&parse(MODEL), &parse(&changed));
+        assert!(matches!(
+            diff.changes[..],
+            [Change::ConditionChanged { .. }]
+        ));
+        assert!(diff.is_breaking());
+    }
+
+    #[test]
+    fn reports_schema_version_changes() {
+        let old = parse("type user\ntype doc\n  relations\n    define viewer as self\n");
+        let new = parse(
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user]\n",
+        );
+        let diff = diff_models(&old, &new);
+        assert_eq!(
+            diff.changes[0],
+            Change::SchemaVersionChanged {
+                old: "1.0".into(),
+                new: "1.1".into()
+            }
+        );
+        // A `self` is not known to contain any type restriction
+        assert_eq!(effect(&diff.changes[1]), AccessEffect::Modified);
+    }
+}
@@ -0,0 +1,865 @@
+use openfga_common::{Access, AuthorizationModel, Identifier, Relation, TypeRestriction};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use thiserror::Error;
+
+/// Maximum number of relations resolved in a row before evaluation is aborted,
+/// mirrors the default resolve depth of the OpenFGA server.
+pub const MAX_RESOLUTION_DEPTH: usize = 25;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tuple {
+    pub user: String,
+    pub relation: String,
+    pub object: String,
+}
+
+impl Tuple {
+    pub fn new(user: &str, relation: &str, object: &str) -> Self {
+        Tuple {
+            user: user.into(),
+            relation: relation.into(),
+            object: object.into(),
+        }
+    }
+}
+
+/// In-memory set of relationship tuples, indexed by object and relation.
+#[derive(Debug, Default, Clone)]
+pub struct TupleStore {
+    tuples: HashMap<(String, String), Vec<String>>,
+}
+
+impl TupleStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, tuple: Tuple) {
+        let users = self
+            .tuples
+            .entry((tuple.object, tuple.relation))
+            .or_default();
+        if !users.contains(&tuple.user) {
+            users.push(tuple.user);
+        }
+    }
+
+    /// Returns the users directly related to the object via the relation.
+    pub fn users(&self, object: &str, relation: &str) -> &[String] {
+        self.tuples
+            .get(&(object.to_string(), relation.to_string()))
+            .map(|users| users.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Tuple> + '_ {
+        self.tuples.iter().flat_map(|((object, relation), users)| {
+            users
+                .iter()
+                .map(move |user| Tuple::new(user, relation, object))
+        })
+    }
+
+    /// Returns every object of the given type mentioned in the store, either
+    /// as object of a tuple or as its user.
+    pub fn objects_of_type(&self, type_name: &str) -> BTreeSet<String> {
+        self.tuples
+            .iter()
+            .flat_map(|((object, _), users)| {
+                std::iter::once(object.as_str()).chain(
+                    users
+                        .iter()
+                        .map(|u| split_userset(u).map_or(u.as_str(), |(o, _)| o)),
+                )
+            })
+            .filter(|object| matches!(split_object(object), Ok((t, _)) if t == type_name))
+            .map(|object| object.to_string())
+            .collect()
+    }
+}
+
+impl FromIterator<Tuple> for TupleStore {
+    fn from_iter<I: IntoIterator<Item = Tuple>>(iter: I) -> Self {
+        let mut store = TupleStore::new();
+        store.extend(iter);
+        store
+    }
+}
+
+impl Extend<Tuple> for TupleStore {
+    fn extend<I: IntoIterator<Item = Tuple>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|t| self.add(t));
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EvaluationError {
+    #[error("Object {0} is invalid, expected the format type:id")]
+    InvalidObject(String),
+    #[error("Type {0} does not exist in the model")]
+    UnknownType(String),
+    #[error("Relation {relation} does not exist on type {type_name}")]
+    UnknownRelation { type_name: String, relation: String },
+    #[error("Resolution exceeded the maximum depth of {}", MAX_RESOLUTION_DEPTH)]
+    ResolutionTooComplex,
+    #[error("Relation {relation} of {object} is only granted to {user} with condition {condition}, which can't be evaluated")]
+    UnsupportedCondition {
+        user: String,
+        relation: String,
+        object: String,
+        condition: String,
+    },
+}
+
+/// Splits an object of the form `type:id` into its type and id.
+pub fn split_object(object: &str) -> Result<(&str, &str), EvaluationError> {
+    match object.split_once(':') {
+        Some((type_name, id)) if !type_name.is_empty() && !id.is_empty() => Ok((type_name, id)),
+        _ => Err(EvaluationError::InvalidObject(object.into())),
+    }
+}
+
+/// Splits a user of the form `type:id#relation` into the object and relation
+/// of the userset, returns `None` if the user is not a userset.
+pub fn split_userset(user: &str) -> Option<(&str, &str)> {
+    user.split_once('#')
+}
+
+fn user_matches(stored_user: &str, user: &str) -> bool {
+    if stored_user == user || stored_user == "*" {
+        return true;
+    }
+    match (stored_user.strip_suffix(":*"), split_object(user)) {
+        (Some(wildcard_type), Ok((user_type, _))) => {
+            wildcard_type == user_type && split_userset(user).is_none()
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn find_relation<'a>(
+    model: &'a AuthorizationModel,
+    type_name: &str,
+    relation: &str,
+) -> Result<&'a Relation, EvaluationError> {
+    let rtype = model
+        .types
+        .iter()
+        .find(|t| t.identifier.name == type_name)
+        .ok_or_else(|| EvaluationError::UnknownType(type_name.into()))?;
+    rtype
+        .relations
+        .iter()
+        .find(|r| r.identifier.name == relation)
+        .ok_or_else(|| EvaluationError::UnknownRelation {
+            type_name: type_name.into(),
+            relation: relation.into(),
+        })
+}
+
+/// Userset `object#relation` on the current resolution path of a check, the
+/// user being checked stays the same along the path.
+type PathEntry = (String, String);
+
+fn check_relation(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    user: &str,
+    relation: &str,
+    object: &str,
+    path: &mut Vec<PathEntry>,
+) -> Result<bool, EvaluationError> {
+    if path.len() >= MAX_RESOLUTION_DEPTH {
+        return Err(EvaluationError::ResolutionTooComplex);
+    }
+    // A userset which is already being resolved can't grant the user
+    // anything new, cyclic tuples resolve to false like in OpenFGA
+    let entry = (object.to_string(), relation.to_string());
+    if path.contains(&entry) {
+        return Ok(false);
+    }
+    let (type_name, _) = split_object(object)?;
+    let rel = find_relation(model, type_name, relation)?;
+    path.push(entry);
+    let result = check_access(model, store, &rel.access, user, relation, object, path);
+    path.pop();
+    result
+}
+
+/// Returns the object of the userset or object stored as user of a tuple, if
+/// it has the relation in the model. Tuples which don't fit the model are
+/// skipped during evaluation instead of failing it.
+fn related_object<'a>(
+    model: &AuthorizationModel,
+    stored_user: &'a str,
+    relation: &str,
+) -> Option<&'a str> {
+    let object = split_userset(stored_user).map_or(stored_user, |(o, _)| o);
+    let (type_name, _) = split_object(object).ok()?;
+    model
+        .type_relation_exists(type_name, relation)
+        .then_some(object)
+}
+
+/// Returns the condition a tuple with the stored user needs, if every type
+/// restriction allowing it is conditional.
+fn required_condition<'a>(
+    restrictions: &'a [TypeRestriction],
+    stored_user: &str,
+) -> Option<&'a Identifier> {
+    let (object, userset_relation) = match split_userset(stored_user) {
+        Some((object, relation)) => (object, Some(relation)),
+        None => (stored_user, None),
+    };
+    let (type_name, id) = split_object(object).ok()?;
+    let mut condition = None;
+    for restriction in restrictions.iter().filter(|r| {
+        r.type_identifier.name == type_name
+            && r.wildcard == (id == "*")
+            && r.relation.as_ref().map(|i| i.name.as_str()) == userset_relation
+    }) {
+        match &restriction.condition {
+            Some(identifier) => condition = Some(identifier),
+            None => return None,
+        }
+    }
+    condition
+}
+
+fn check_access(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    access: &Access,
+    user: &str,
+    relation: &str,
+    object: &str,
+    path: &mut Vec<PathEntry>,
+) -> Result<bool, EvaluationError> {
+    match access {
+        Access::Direct {
+            restrictions,
+            span: _,
+        } => {
+            // Tuples carry no condition context, so a grant which depends on
+            // a condition can't be decided
+            let granted = |stored_user: &str| match required_condition(restrictions, stored_user) {
+                Some(condition) => Err(EvaluationError::UnsupportedCondition {
+                    user: user.to_string(),
+                    relation: relation.to_string(),
+                    object: object.to_string(),
+                    condition: condition.name.clone(),
+                }),
+                None => Ok(true),
+            };
+            for stored_user in store.users(object, relation) {
+                if user_matches(stored_user, user) {
+                    return granted(stored_user);
+                }
+                let (userset_object, userset_relation) = match split_userset(stored_user) {
+                    Some(userset) => userset,
+                    None => continue,
+                };
+                if related_object(model, userset_object, userset_relation).is_some()
+                    && check_relation(model, store, user, userset_relation, userset_object, path)?
+                {
+                    return granted(stored_user);
+                }
+            }
+            Ok(false)
+        }
+        Access::SelfComputed {
+            relation: computed,
+            span: _,
+        } => check_relation(model, store, user, &computed.name, object, path),
+        Access::Computed {
+            object: tupleset,
+            relation: computed,
+            span: _,
+        } => {
+            for parent in store.users(object, &tupleset.name) {
+                let parent = match related_object(model, parent, &computed.name) {
+                    Some(parent) => parent,
+                    None => continue,
+                };
+                if check_relation(model, store, user, &computed.name, parent, path)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        Access::Union { children, span: _ } => {
+            for child in children {
+                if check_access(model, store, child, user, relation, object, path)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        Access::Intersection { children, span: _ } => {
+            for child in children {
+                if !check_access(model, store, child, user, relation, object, path)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        Access::Difference {
+            base,
+            subtract,
+            span: _,
+        } => Ok(
+            check_access(model, store, base, user, relation, object, path)?
+                && !check_access(model, store, subtract, user, relation, object, path)?,
+        ),
+    }
+}
+
+/// Checks whether the user has the relation on the object, given the model
+/// and the tuples in the store. Conditions are not evaluated, a tuple granting
+/// the relation only through type restrictions with a condition fails the
+/// check with [`EvaluationError::UnsupportedCondition`].
+pub fn check(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    user: &str,
+    relation: &str,
+    object: &str,
+) -> Result<bool, EvaluationError> {
+    check_relation(model, store, user, relation, object, &mut Vec::new())
+}
+
+/// Userset tree of a relation on an object, mirrors the structure of the
+/// relation's `Access` with the users from the tuple store at its leaves. Like
+/// the Expand API of OpenFGA it only covers one level, usersets at the leaves
+/// are left for the caller to expand further.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum UsersetTree {
+    /// Users directly related to the userset, including usersets like
+    /// `group:eng#member`.
+    Direct {
+        userset: String,
+        users: Vec<String>,
+    },
+    /// Another userset of the object, usually `object#relation`.
+    Computed {
+        userset: String,
+    },
+    /// Tupleset `object#relation` and the computed usersets of every object
+    /// related through it.
+    TupleToUserset {
+        tupleset: String,
+        computed: Vec<String>,
+    },
+    Union {
+        children: Vec<UsersetTree>,
+    },
+    Intersection {
+        children: Vec<UsersetTree>,
+    },
+    Difference {
+        base: Box<UsersetTree>,
+        subtract: Box<UsersetTree>,
+    },
+}
+
+impl UsersetTree {
+    /// Returns every user listed at a direct leaf of the tree, usersets
+    /// included, regardless of whether the set operations of the tree grant
+    /// it access.
+    pub fn leaf_users(&self) -> BTreeSet<String> {
+        let mut users = BTreeSet::new();
+        self.collect_leaf_users(&mut users);
+        users
+    }
+
+    fn collect_leaf_users(&self, users: &mut BTreeSet<String>) {
+        match self {
+            UsersetTree::Direct {
+                userset: _,
+                users: direct,
+            } => users.extend(direct.iter().cloned()),
+            UsersetTree::Computed { userset: _ }
+            | UsersetTree::TupleToUserset {
+                tupleset: _,
+                computed: _,
+            } => (),
+            UsersetTree::Union { children } | UsersetTree::Intersection { children } => {
+                children.iter().for_each(|c| c.collect_leaf_users(users))
+            }
+            UsersetTree::Difference { base, subtract } => {
+                base.collect_leaf_users(users);
+                subtract.collect_leaf_users(users);
+            }
+        }
+    }
+}
+
+fn expand_access(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    access: &Access,
+    relation: &str,
+    object: &str,
+) -> UsersetTree {
+    match access {
+        Access::Direct { .. } => UsersetTree::Direct {
+            userset: format!("{}#{}", object, relation),
+            users: store.users(object, relation).to_vec(),
+        },
+        Access::SelfComputed {
+            relation: computed,
+            span: _,
+        } => UsersetTree::Computed {
+            userset: format!("{}#{}", object, computed.name),
+        },
+        Access::Computed {
+            object: tupleset,
+            relation: computed,
+            span: _,
+        } => UsersetTree::TupleToUserset {
+            tupleset: format!("{}#{}", object, tupleset.name),
+            computed: store
+                .users(object, &tupleset.name)
+                .iter()
+                .filter_map(|parent| related_object(model, parent, &computed.name))
+                .map(|parent| format!("{}#{}", parent, computed.name))
+                .collect(),
+        },
+        Access::Union { children, span: _ } => UsersetTree::Union {
+            children: children
+                .iter()
+                .map(|c| expand_access(model, store, c, relation, object))
+                .collect(),
+        },
+        Access::Intersection { children, span: _ } => UsersetTree::Intersection {
+            children: children
+                .iter()
+                .map(|c| expand_access(model, store, c, relation, object))
+                .collect(),
+        },
+        Access::Difference {
+            base,
+            subtract,
+            span: _,
+        } => UsersetTree::Difference {
+            base: Box::new(expand_access(model, store, base, relation, object)),
+            subtract: Box::new(expand_access(model, store, subtract, relation, object)),
+        },
+    }
+}
+
+/// Expands the relation on the object into the userset tree describing who
+/// has the relation and why, one level deep.
+pub fn expand(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    relation: &str,
+    object: &str,
+) -> Result<UsersetTree, EvaluationError> {
+    let (type_name, _) = split_object(object)?;
+    let rel = find_relation(model, type_name, relation)?;
+    Ok(expand_access(model, store, &rel.access, relation, object))
+}
+
+/// Lists every object of the object type on which the user has the relation.
+/// Objects whose check fails, e.g. because it gets too deep, are left out
+/// instead of failing the whole list.
+pub fn list_objects(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    user: &str,
+    relation: &str,
+    object_type: &str,
+) -> Result<Vec<String>, EvaluationError> {
+    find_relation(model, object_type, relation)?;
+    Ok(store
+        .objects_of_type(object_type)
+        .into_iter()
+        .filter(|object| check(model, store, user, relation, object) == Ok(true))
+        .collect())
+}
+
+/// Lists every user of the user type which has the relation on the object.
+/// Users whose check fails are left out like in `list_objects`.
+pub fn list_users(
+    model: &AuthorizationModel,
+    store: &TupleStore,
+    relation: &str,
+    object: &str,
+    user_type: &str,
+) -> Result<Vec<String>, EvaluationError> {
+    let (type_name, _) = split_object(object)?;
+    find_relation(model, type_name, relation)?;
+    Ok(store
+        .objects_of_type(user_type)
+        .into_iter()
+        .filter(|user| check(model, store, user, relation, object) == Ok(true))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openfga_model_dsl_parser::parse_model;
+
+    const MODEL: &str = "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user, user:*, group#member]
+type folder
+  relations
+    define owner: [user]
+    define viewer: [user] or owner
+type doc
+  relations
+    define parent: [folder]
+    define owner: [user]
+    define editor: [user, group#member] or owner
+    define viewer: [user] or editor or viewer from parent
+    define blocked: [user]
+    define approver: [user]
+    define reviewer: editor and approver
+    define reader: viewer but not blocked
+";
+
+    fn model() -> AuthorizationModel {
+        parse_model(MODEL).unwrap().0
+    }
+
+    fn store(tuples: &[(&str, &str, &str)]) -> TupleStore {
+        tuples
+            .iter()
+            .map(|(user, relation, object)| Tuple::new(user, relation, object))
+            .collect()
+    }
+
+    #[test]
+    fn checks_direct_tuples() {
+        let store = store(&[("user:anne", "owner", "doc:1")]);
+        assert_eq!(
+            check(&model(), &store, "user:anne", "owner", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model(), &store, "user:bob", "owner", "doc:1"),
+            Ok(false)
+        );
+        assert_eq!(
+            check(&model(), &store, "user:anne", "owner", "doc:2"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn checks_computed_usersets() {
+        let store = store(&[("user:anne", "owner", "doc:1")]);
+        assert_eq!(
+            check(&model(), &store, "user:anne", "editor", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model(), &store, "user:anne", "viewer", "doc:1"),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn checks_userset_tuples() {
+        let store = store(&[
+            ("user:anne", "member", "group:eng"),
+            ("group:eng#member", "editor", "doc:1"),
+        ]);
+        assert_eq!(
+            check(&model(), &store, "user:anne", "editor", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model(), &store, "user:bob", "editor", "doc:1"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn checks_tuple_to_userset() {
+        let store = store(&[
+            ("folder:x", "parent", "doc:1"),
+            ("user:anne", "owner", "folder:x"),
+        ]);
+        assert_eq!(
+            check(&model(), &store, "user:anne", "viewer", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model(), &store, "user:bob", "viewer", "doc:1"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn checks_intersection_and_difference() {
+        let store = store(&[
+            ("user:anne", "owner", "doc:1"),
+            ("user:anne", "approver", "doc:1"),
+            ("user:bob", "owner", "doc:1"),
+            ("user:bob", "blocked", "doc:1"),
+        ]);
+        let model = model();
+        assert_eq!(
+            check(&model, &store, "user:anne", "reviewer", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model, &store, "user:bob", "reviewer", "doc:1"),
+            Ok(false)
+        );
+        assert_eq!(
+            check(&model, &store, "user:anne", "reader", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model, &store, "user:bob", "reader", "doc:1"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn checks_wildcards() {
+        let store = store(&[("user:*", "member", "group:all")]);
+        let model = model();
+        assert_eq!(
+            check(&model, &store, "user:anne", "member", "group:all"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model, &store, "group:eng#member", "member", "group:all"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn cyclic_usersets_resolve_to_false() {
+        let store = store(&[
+            ("group:a#member", "member", "group:b"),
+            ("group:b#member", "member", "group:a"),
+            ("user:bob", "member", "group:b"),
+        ]);
+        let model = model();
+        assert_eq!(
+            check(&model, &store, "user:anne", "member", "group:a"),
+            Ok(false)
+        );
+        assert_eq!(
+            check(&model, &store, "user:bob", "member", "group:a"),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn skips_tuples_which_do_not_fit_the_model() {
+        let store = store(&[
+            ("invalid", "parent", "doc:1"),
+            ("group:eng#unknown", "editor", "doc:1"),
+            ("folder:x", "parent", "doc:1"),
+            ("user:anne", "owner", "folder:x"),
+        ]);
+        let model = model();
+        assert_eq!(
+            check(&model, &store, "user:anne", "viewer", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model, &store, "user:bob", "viewer", "doc:1"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn expands_one_level() {
+        let store = store(&[
+            ("user:anne", "viewer", "doc:1"),
+            ("group:eng#member", "editor", "doc:1"),
+            ("folder:x", "parent", "doc:1"),
+            ("invalid", "parent", "doc:1"),
+        ]);
+        let tree = expand(&model(), &store, "viewer", "doc:1").unwrap();
+        assert_eq!(
+            tree,
+            UsersetTree::Union {
+                children: vec![
+                    UsersetTree::Direct {
+                        userset: "doc:1#viewer".into(),
+                        users: vec!["user:anne".into()],
+                    },
+                    UsersetTree::Computed {
+                        userset: "doc:1#editor".into(),
+                    },
+                    UsersetTree::TupleToUserset {
+                        tupleset: "doc:1#parent".into(),
+                        computed: vec!["folder:x#viewer".into()],
+                    },
+                ],
+            }
+        );
+        assert_eq!(tree.leaf_users(), BTreeSet::from(["user:anne".to_string()]));
+    }
+
+    #[test]
+    fn expands_cyclic_usersets() {
+        let store = store(&[
+            ("group:a#member", "member", "group:b"),
+            ("group:b#member", "member", "group:a"),
+        ]);
+        assert_eq!(
+            expand(&model(), &store, "member", "group:a"),
+            Ok(UsersetTree::Direct {
+                userset: "group:a#member".into(),
+                users: vec!["group:b#member".into()],
+            })
+        );
+    }
+
+    #[test]
+    fn lists_objects() {
+        let store = store(&[
+            ("group:a#member", "member", "group:b"),
+            ("group:b#member", "member", "group:a"),
+            ("user:anne", "member", "group:c"),
+            ("group:c#member", "member", "group:d"),
+            ("user:anne", "owner", "folder:x"),
+            ("folder:x", "parent", "doc:1"),
+            ("folder:y", "parent", "doc:2"),
+        ]);
+        let model = model();
+        assert_eq!(
+            list_objects(&model, &store, "user:anne", "member", "group"),
+            Ok(vec!["group:c".to_string(), "group:d".to_string()])
+        );
+        assert_eq!(
+            list_objects(&model, &store, "user:x", "member", "group"),
+            Ok(vec![])
+        );
+        assert_eq!(
+            list_objects(&model, &store, "user:anne", "viewer", "doc"),
+            Ok(vec!["doc:1".to_string()])
+        );
+        assert!(list_objects(&model, &store, "user:anne", "unknown", "doc").is_err());
+    }
+
+    #[test]
+    fn lists_objects_without_the_ones_failing_to_check() {
+        let mut store: TupleStore = (1..=MAX_RESOLUTION_DEPTH)
+            .map(|i| {
+                Tuple::new(
+                    &format!("group:{}#member", i),
+                    "member",
+                    &format!("group:{}", i - 1),
+                )
+            })
+            .collect();
+        store.add(Tuple::new(
+            "user:anne",
+            "member",
+            &format!("group:{}", MAX_RESOLUTION_DEPTH),
+        ));
+        let model = model();
+        assert_eq!(
+            check(&model, &store, "user:anne", "member", "group:0"),
+            Err(EvaluationError::ResolutionTooComplex)
+        );
+        let objects = list_objects(&model, &store, "user:anne", "member", "group").unwrap();
+        assert!(!objects.contains(&"group:0".to_string()));
+        assert!(objects.contains(&format!("group:{}", MAX_RESOLUTION_DEPTH)));
+    }
+
+    #[test]
+    fn lists_users() {
+        let store = store(&[
+            ("group:a#member", "member", "group:b"),
+            ("group:b#member", "member", "group:a"),
+            ("user:anne", "member", "group:c"),
+            ("user:bob", "member", "group:a"),
+            ("group:c#member", "editor", "doc:1"),
+            ("user:carl", "owner", "doc:1"),
+        ]);
+        let model = model();
+        assert_eq!(
+            list_users(&model, &store, "viewer", "doc:1", "user"),
+            Ok(vec!["user:anne".to_string(), "user:carl".to_string()])
+        );
+        assert_eq!(
+            list_users(&model, &store, "member", "group:b", "user"),
+            Ok(vec!["user:bob".to_string()])
+        );
+        assert!(list_users(&model, &store, "viewer", "doc", "user").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_relations_and_objects() {
+        let store = TupleStore::new();
+        let model = model();
+        assert_eq!(
+            check(&model, &store, "user:anne", "unknown", "doc:1"),
+            Err(EvaluationError::UnknownRelation {
+                type_name: "doc".into(),
+                relation: "unknown".into()
+            })
+        );
+        assert_eq!(
+            check(&model, &store, "user:anne", "owner", "doc"),
+            Err(EvaluationError::InvalidObject("doc".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_grants_depending_on_conditions() {
+        let (model, _) = parse_model(
+            "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user]
+type doc
+  relations
+    define viewer: [user with in_hours, group#member with in_hours, group#member]
+    define editor: [user, user with in_hours]
+condition in_hours(hour: int) {
+  hour > 9
+}
+",
+        )
+        .unwrap();
+        let store = store(&[
+            ("user:anne", "viewer", "doc:1"),
+            ("group:eng#member", "viewer", "doc:1"),
+            ("user:bob", "member", "group:eng"),
+            ("user:anne", "editor", "doc:1"),
+        ]);
+        assert_eq!(
+            check(&model, &store, "user:anne", "viewer", "doc:1"),
+            Err(EvaluationError::UnsupportedCondition {
+                user: "user:anne".into(),
+                relation: "viewer".into(),
+                object: "doc:1".into(),
+                condition: "in_hours".into()
+            })
+        );
+        // Usersets and users which may be related without a condition are
+        // granted as usual
+        assert_eq!(
+            check(&model, &store, "user:bob", "viewer", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model, &store, "user:anne", "editor", "doc:1"),
+            Ok(true)
+        );
+        assert_eq!(
+            check(&model, &store, "user:carl", "viewer", "doc:1"),
+            Ok(false)
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use openfga_common::{Access, AuthorizationModel, Comment, Condition, Relation, Type};
+use openfga_model_dsl_parser::{parse_model, ParseErrors};
+
+/// Relations longer than this get their top level `or` or `and` chain broken
+/// up into one operand per line.
+pub const MAX_LINE_WIDTH: usize = 100;
+
+const RELATION_INDENT: usize = 4;
+const CONTINUATION_INDENT: usize = 6;
+
+/// Formats the source of a model into its canonical form, keeping its `//`
+/// comments.
+pub fn format_model(src: &str) -> Result<String, ParseErrors> {
+    let (model, _) = parse_model(src)?;
+    Ok(format(&model))
+}
+
+/// Prints the model in its canonical form, along with the comments attached
+/// to it.
+pub fn format(model: &AuthorizationModel) -> String {
+    let mut formatter = Formatter {
+        output: String::new(),
+    };
+    formatter.format(model);
+    formatter.output
+}
+
+enum Definition<'a> {
+    Type(&'a Type),
+    Condition(&'a Condition),
+}
+
+impl Definition<'_> {
+    fn start(&self) -> usize {
+        let span = match self {
+            Definition::Type(t) => &t.span,
+            Definition::Condition(c) => &c.span,
+        };
+        span.as_ref().map_or(0, |span| span.start)
+    }
+}
+
+struct Formatter {
+    output: String,
+}
+
+impl Formatter {
+    fn write_comment_lines(&mut self, comments: &[Comment], indent: usize) {
+        for comment in comments {
+            self.output += &format!("{}{}\n", " ".repeat(indent), comment.text);
+        }
+    }
+
+    fn write_line(&mut self, indent: usize, text: &str, trailing: &[Comment]) {
+        self.output += &" ".repeat(indent);
+        self.output += text;
+        for comment in trailing {
+            self.output += " ";
+            self.output += &comment.text;
+        }
+        self.output += "\n";
+    }
+
+    fn format(&mut self, model: &AuthorizationModel) {
+        if let Some(module) = &model.module {
+            self.write_comment_lines(&model.header_comments.leading, 0);
+            self.write_line(
+                0,
+                &format!("module {}", module.name),
+                &model.header_comments.trailing,
+            );
+        } else if let Some(schema_version) = &model.schema_version {
+            self.write_comment_lines(&model.header_comments.leading, 0);
+            self.output += "model\n";
+            self.write_line(
+                2,
+                &format!("schema {}", schema_version),
+                &model.header_comments.trailing,
+            );
+        }
+
+        // Definitions keep their order in the source, models without spans
+        // list their types first
+        let mut definitions: Vec<Definition> = model
+            .types
+            .iter()
+            .map(Definition::Type)
+            .chain(model.conditions.iter().map(Definition::Condition))
+            .collect();
+        definitions.sort_by_key(|d| d.start());
+
+        let legacy = model.is_legacy_schema();
+        for definition in definitions {
+            if !self.output.is_empty() {
+                self.output += "\n";
+            }
+            match definition {
+                Definition::Type(t) => self.format_type(t, legacy),
+                Definition::Condition(c) => self.format_condition(c),
+            }
+        }
+
+        self.write_comment_lines(&model.end_comments, 0);
+    }
+
+    fn format_type(&mut self, t: &Type, legacy: bool) {
+        self.write_comment_lines(&t.comments.leading, 0);
+        let keyword = if t.extension { "extend type" } else { "type" };
+        self.write_line(
+            0,
+            &format!("{} {}", keyword, t.identifier.name),
+            &t.comments.trailing,
+        );
+        if t.relations.is_empty() {
+            return;
+        }
+        self.output += "  relations\n";
+        for relation in &t.relations {
+            self.format_relation(relation, legacy);
+        }
+    }
+
+    fn format_relation(&mut self, relation: &Relation, legacy: bool) {
+        self.write_comment_lines(&relation.comments.leading, RELATION_INDENT);
+        let prefix = if legacy {
+            format!("define {} as ", relation.identifier.name)
+        } else {
+            format!("define {}: ", relation.identifier.name)
+        };
+        let lines = format_access(&relation.access, RELATION_INDENT + prefix.len());
+        let last = lines.len() - 1;
+        for (i, line) in lines.iter().enumerate() {
+            let (indent, text) = if i == 0 {
+                (RELATION_INDENT, format!("{}{}", prefix, line))
+            } else {
+                (CONTINUATION_INDENT, line.clone())
+            };
+            // Comments are only written after the last line of the relation
+            let trailing: &[Comment] = if i == last {
+                &relation.comments.trailing
+            } else {
+                &[]
+            };
+            self.write_line(indent, &text, trailing);
+        }
+    }
+
+    fn format_condition(&mut self, condition: &Condition) {
+        self.write_comment_lines(&condition.comments.leading, 0);
+        let parameters: Vec<String> = condition
+            .parameters
+            .iter()
+            .map(|p| format!("{}: {}", p.identifier.name, p.parameter_type))
+            .collect();
+        self.output += &format!(
+            "condition {}({}) {{\n",
+            condition.identifier.name,
+            parameters.join(", ")
+        );
+        for line in condition.body_lines() {
+            self.output += &line;
+            self.output.push('\n');
+        }
+        self.write_line(0, "}", &condition.comments.trailing);
+    }
+}
+
+/// Formats the access, breaking a top level `or` or `and` chain into one line
+/// per operand if it doesn't fit behind the given width.
+fn format_access(access: &Access, width: usize) -> Vec<String> {
+    let single = access.to_string();
+    if width + single.chars().count() <= MAX_LINE_WIDTH {
+        return vec![single];
+    }
+    let (children, operator) = match access {
+        Access::Union { children, span: _ } => (children, "or"),
+        Access::Intersection { children, span: _ } => (children, "and"),
+        _ => return vec![single],
+    };
+    children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| {
+            let operand = if !child.is_simple() {
+                format!("({})", child)
+            } else {
+                child.to_string()
+            };
+            if i == 0 {
+                operand
+            } else {
+                format!("{} {}", operator, operand)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNFORMATTED: &str = "// header
+model
+    schema 1.1 // version
+type user
+type   doc // documents
+ relations
+  // owners of the doc
+  define owner:[user]
+  define viewer : [ user ] or owner // readers
+condition in_hours(hour: int, start: int) {
+    hour > start &&
+      (hour < 18 ||
+        hour > 22)
+}
+// end
+";
+
+    const FORMATTED: &str = "// header
+model
+  schema 1.1 // version
+
+type user
+
+type doc // documents
+  relations
+    // owners of the doc
+    define owner: [user]
+    define viewer: [user] or owner // readers
+
+condition in_hours(hour: int, start: int) {
+  hour > start &&
+    (hour < 18 ||
+      hour > 22)
+}
+// end
+";
+
+    #[test]
+    fn formats_models_keeping_comments() {
+        assert_eq!(format_model(UNFORMATTED).unwrap(), FORMATTED);
+    }
+
+    #[test]
+    fn formats_idempotently() {
+        assert_eq!(format_model(FORMATTED).unwrap(), FORMATTED);
+        let legacy = "type user\n\ntype doc\n  relations\n    define viewer as self\n";
+        assert_eq!(format_model(legacy).unwrap(), legacy);
+    }
+
+    #[test]
+    fn breaks_long_or_chains() {
+        let names: Vec<String> = (1..=8).map(|i| format!("relation_number_{}", i)).collect();
+        let mut src = String::from("model\n  schema 1.1\ntype user\ntype doc\n  relations\n");
+        for name in &names {
+            src += &format!("    define {}: [user]\n", name);
+        }
+        src += &format!("    define viewer: {}\n", names.join(" or "));
+        let formatted = format_model(&src).unwrap();
+        let expected = format!(
+            "    define viewer: {}\n{}",
+            names[0],
+            names[1..]
+                .iter()
+                .map(|name| format!("      or {}\n", name))
+                .collect::<String>()
+        );
+        assert!(formatted.ends_with(&expected), "{}", formatted);
+        assert!(formatted.lines().all(|l| l.len() <= MAX_LINE_WIDTH));
+        assert_eq!(format_model(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn keeps_conditions_written_on_one_line() {
+        let src = "model\n  schema 1.1\ntype user\ncondition c(a: int) { a > 1 }\n";
+        assert!(format_model(src)
+            .unwrap()
+            .ends_with("condition c(a: int) {\n  a > 1\n}\n"));
+    }
+
+    #[test]
+    fn keeps_blank_lines_inside_of_conditions() {
+        let src = "model\n  schema 1.1\ntype user\ncondition c(a: int) {\n      a > 1 &&\n\n        a < 5   \n}\n";
+        let formatted = format_model(src).unwrap();
+        assert!(formatted.ends_with("condition c(a: int) {\n  a > 1 &&\n\n    a < 5\n}\n"));
+        assert_eq!(format_model(&formatted).unwrap(), formatted);
+    }
+}
@@ -1,21 +1,49 @@
 use dashmap::DashMap;
-use openfga_checker::{check_model, ModelError};
-use openfga_common::AuthorizationModel;
-use openfga_model_dsl_parser::{parse_model, Token};
+use openfga_checker::{
+    check_model_with_config, is_tupleset, rename_symbol, symbol_at, symbol_at_in_file,
+    symbol_definitions, symbol_references, tupleset_types, Config, ModelError, Severity, Symbol,
+};
+use openfga_common::{
+    AuthorizationModel, Comments, FileId, Identifier, Relation, SourceMap, Span, Type,
+};
+use openfga_formatter::format_model;
+use openfga_model_dsl_parser::{
+    find_manifest, is_identifier, parse_model_recovery, parse_modules, Manifest, Spanned, Token,
+};
 use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::fs;
 use std::ops::Range as OpsRange;
-use tower_lsp::jsonrpc::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
+/// Fix of a diagnostic, sent to the client in the `data` field of the
+/// diagnostic and turned into a code action once the client asks for them.
+#[derive(Serialize, Deserialize)]
+struct Fix {
+    title: String,
+    edits: Vec<TextEdit>,
+    preferred: bool,
+}
+
 #[derive(Debug)]
 struct Backend {
     client: Client,
-    diagnostics_map: DashMap<String, Option<Vec<ModelError>>>,
+    diagnostics_map: DashMap<String, Option<Vec<(ModelError, Severity)>>>,
+    /// Files the findings of the document point into, named by their uri
+    source_map_map: DashMap<String, Arc<SourceMap>>,
+    /// Model of the modular model the document is a file of, along with the
+    /// id of the document in its source map, as long as every file parses
+    module_model_map: DashMap<String, (Arc<AuthorizationModel>, FileId)>,
+    syntax_errors_map: DashMap<String, Vec<(OpsRange<usize>, String)>>,
     model_map: DashMap<String, Option<AuthorizationModel>>,
     rope_map: DashMap<String, Option<Rope>>,
-    token_map: DashMap<String, Option<Vec<(Token, OpsRange<usize>)>>>,
+    token_map: DashMap<String, Option<Vec<Spanned<Token>>>>,
 }
 
 #[tower_lsp::async_trait]
@@ -25,7 +53,7 @@ impl LanguageServer for Backend {
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
                     TextDocumentSyncOptions {
-                        change: Some(TextDocumentSyncKind::FULL),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
                         open_close: Some(true),
                         save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
                             include_text: Some(true),
@@ -34,7 +62,18 @@ impl LanguageServer for Backend {
                     },
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions {
+                        work_done_progress: Some(false),
+                    },
+                })),
                 completion_provider: Some(CompletionOptions::default()),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
                 document_symbol_provider: Some(OneOf::Right(DocumentSymbolOptions {
                     label: Some("OpenFGA".into()),
                     work_done_progress_options: WorkDoneProgressOptions {
@@ -58,6 +97,9 @@ impl LanguageServer for Backend {
                                     SemanticTokenType::OPERATOR,
                                     SemanticTokenType::CLASS,
                                     SemanticTokenType::METHOD,
+                                    SemanticTokenType::NUMBER,
+                                    SemanticTokenType::STRING,
+                                    SemanticTokenType::COMMENT,
                                 ],
                                 token_modifiers: vec![],
                             },
@@ -82,17 +124,166 @@ impl LanguageServer for Backend {
         Ok(())
     }
 
-    async fn completion(&self, _: CompletionParams) -> Result<Option<CompletionResponse>> {
-        Ok(Some(CompletionResponse::Array(vec![
-            CompletionItem::new_simple("type".to_string(), "New type".to_string()),
-            CompletionItem::new_simple("define".to_string(), "New relation".to_string()),
-        ])))
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = params.text_document_position.text_document.uri;
+        let offset = match self.position_to_char(&uri, params.text_document_position.position) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let text = match self.rope_map.get(&uri.to_string()) {
+            Some(rope_ref) => match rope_ref.as_ref() {
+                Some(rope) => rope.to_string(),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let tokens_ref = match self.token_map.get(&uri.to_string()) {
+            Some(tokens_ref) => tokens_ref,
+            None => return Ok(None),
+        };
+        let tokens = match tokens_ref.as_ref() {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let model_ref = self.model_map.get(&uri.to_string());
+        let model = model_ref.as_ref().and_then(|m| m.value().as_ref());
+        Ok(Some(CompletionResponse::Array(completion_items(
+            model, tokens, &text, offset,
+        ))))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let hover = self.with_symbol(
+            &uri,
+            params.text_document_position_params.position,
+            |model, symbol, locate| {
+                let value = hover_contents(model, symbol)?;
+                Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value,
+                    }),
+                    range: symbol
+                        .identifier()
+                        .span
+                        .as_ref()
+                        .and_then(locate)
+                        .map(|location| location.range),
+                })
+            },
+        );
+        Ok(hover.flatten())
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let locations = self.lookup_symbol(
+            &uri,
+            params.text_document_position_params.position,
+            symbol_definitions,
+        );
+        Ok(locations
+            .filter(|l| !l.is_empty())
+            .map(GotoDefinitionResponse::Array))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let include_declaration = params.context.include_declaration;
+        Ok(self.lookup_symbol(
+            &uri,
+            params.text_document_position.position,
+            |model, symbol| {
+                let mut identifiers = symbol_references(model, symbol);
+                if include_declaration {
+                    identifiers.splice(0..0, symbol_definitions(model, symbol));
+                }
+                identifiers
+            },
+        ))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let definitions = self.lookup_symbol(&uri, position, symbol_definitions);
+        let references = self.lookup_symbol(&uri, position, symbol_references);
+        // Only the occurrences in the document itself are highlighted
+        let highlights = definitions
+            .into_iter()
+            .flatten()
+            .map(|location| (location, DocumentHighlightKind::WRITE))
+            .chain(
+                references
+                    .into_iter()
+                    .flatten()
+                    .map(|location| (location, DocumentHighlightKind::READ)),
+            )
+            .filter(|(location, _)| location.uri == uri)
+            .map(|(location, kind)| DocumentHighlight {
+                range: location.range,
+                kind: Some(kind),
+            })
+            .collect();
+        Ok(Some(highlights))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let uri = params.text_document.uri;
+        let locations = self.lookup_symbol(&uri, params.position, |_, symbol| {
+            vec![symbol.identifier().clone()]
+        });
+        Ok(locations
+            .and_then(|locations| locations.into_iter().next())
+            .map(|location| PrepareRenameResponse::Range(location.range)))
     }
 
-    async fn hover(&self, _: HoverParams) -> Result<Option<Hover>> {
-        Ok(Some(Hover {
-            contents: HoverContents::Scalar(MarkedString::String("You're hovering!".to_string())),
-            range: None,
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let new_name = params.new_name;
+        if !is_identifier(&new_name) {
+            return Err(Error::invalid_params(format!(
+                "{} is not a valid name",
+                new_name
+            )));
+        }
+        let locations = self.with_symbol(
+            &uri,
+            params.text_document_position.position,
+            |model, symbol, locate| {
+                rename_symbol(model, symbol, &new_name).map(|identifiers| {
+                    identifiers
+                        .iter()
+                        .filter_map(|identifier| locate(identifier.span.as_ref()?))
+                        .collect::<Vec<_>>()
+                })
+            },
+        );
+        let locations = match locations {
+            Some(locations) => locations.map_err(|err| Error::invalid_params(err.to_string()))?,
+            None => return Ok(None),
+        };
+        // Symbols of a modular model are renamed in every file using them
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for location in locations {
+            changes.entry(location.uri).or_default().push(TextEdit {
+                range: location.range,
+                new_text: new_name.clone(),
+            });
+        }
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
         }))
     }
 
@@ -100,8 +291,11 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "File opened!")
             .await;
-        self.on_change(&params.text_document.uri, params.text_document.text)
-            .await;
+        self.on_change(
+            &params.text_document.uri,
+            Rope::from_str(&params.text_document.text),
+        )
+        .await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -111,11 +305,26 @@ impl LanguageServer for Backend {
                 "File changed, parsing and checking model again!",
             )
             .await;
-        self.on_change(
-            &params.text_document.uri,
-            params.content_changes[0].text.clone(),
-        )
-        .await
+        let uri = params.text_document.uri;
+        let mut rope = match self.rope_map.get(&uri.to_string()) {
+            Some(rope_ref) => rope_ref.clone().unwrap_or_default(),
+            None => Rope::new(),
+        };
+        // Changes are applied in order, each one to the result of the previous
+        for change in params.content_changes {
+            match change.range {
+                Some(range) => {
+                    let start = position_to_char(&rope, range.start);
+                    let end = position_to_char(&rope, range.end);
+                    if let (Some(start), Some(end)) = (start, end) {
+                        rope.remove(start..end);
+                        rope.insert(start, &change.text);
+                    }
+                }
+                None => rope = Rope::from_str(&change.text),
+            }
+        }
+        self.on_change(&uri, rope).await
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
@@ -125,88 +334,187 @@ impl LanguageServer for Backend {
                 "File saved, parsing and checking model again!",
             )
             .await;
-        self.on_change(&params.text_document.uri, params.text.unwrap())
+        if let Some(text) = params.text {
+            self.on_change(&params.text_document.uri, Rope::from_str(&text))
+                .await;
+        }
+    }
+
+    async fn did_change_watched_files(&self, _: DidChangeWatchedFilesParams) {
+        self.client
+            .log_message(
+                MessageType::INFO,
+                "Config or manifest changed, checking all models again!",
+            )
             .await;
+        let documents: Vec<(String, Rope)> = self
+            .rope_map
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .value()
+                    .as_ref()
+                    .map(|rope| (entry.key().clone(), rope.clone()))
+            })
+            .collect();
+        for (uri, rope) in documents {
+            if let Ok(uri) = Url::parse(&uri) {
+                self.on_change(&uri, rope).await;
+            }
+        }
     }
 
-    async fn did_close(&self, _: DidCloseTextDocumentParams) {
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
         self.client
             .log_message(MessageType::INFO, "File closed!")
             .await;
+        let uri = params.text_document.uri;
+        let key = uri.to_string();
+        self.diagnostics_map.remove(&key);
+        self.source_map_map.remove(&key);
+        self.module_model_map.remove(&key);
+        self.syntax_errors_map.remove(&key);
+        self.model_map.remove(&key);
+        self.rope_map.remove(&key);
+        self.token_map.remove(&key);
+        match self.load_manifest(&uri).await {
+            // The file is still part of the modular model, the other files
+            // are checked again along with its contents on disk
+            Some((manifest_path, manifest)) => {
+                self.check_workspace(&manifest_path, &manifest).await
+            }
+            None => self.client.publish_diagnostics(uri, Vec::new(), None).await,
+        }
     }
 
+    #[allow(deprecated)]
     async fn document_symbol(
         &self,
         params: DocumentSymbolParams,
     ) -> Result<Option<DocumentSymbolResponse>> {
-        let model_ref = self
-            .model_map
-            .get(&params.text_document.uri.to_string())
-            .unwrap();
+        let uri = params.text_document.uri;
+        let model_ref = match self.model_map.get(&uri.to_string()) {
+            Some(model_ref) => model_ref,
+            None => return Ok(None),
+        };
         let model = match model_ref.as_ref() {
             Some(m) => m,
             None => return Ok(None),
         };
+        // Definitions recovered from a broken document may lack a span
         let res: DocumentSymbolResponse = DocumentSymbolResponse::Nested(
             model
                 .types
                 .iter()
-                .map(|t| DocumentSymbol {
-                    name: t.identifier.name.clone(),
-                    detail: None,
-                    kind: SymbolKind::CLASS,
-                    tags: None,
-                    deprecated: None,
-                    range: self.span_to_range(&params.text_document.uri, t.span.clone().unwrap()),
-                    selection_range: self.span_to_range(
-                        &params.text_document.uri,
-                        t.identifier.span.clone().unwrap(),
-                    ),
-                    children: Some(
-                        t.relations
-                            .iter()
-                            .map(|r| DocumentSymbol {
-                                name: r.identifier.name.clone(),
-                                detail: None,
-                                kind: SymbolKind::METHOD,
-                                tags: None,
-                                deprecated: None,
-                                range: self.span_to_range(
-                                    &params.text_document.uri,
-                                    r.span.clone().unwrap(),
-                                ),
-                                selection_range: self.span_to_range(
-                                    &params.text_document.uri,
-                                    r.identifier.span.clone().unwrap(),
-                                ),
-                                children: None,
-                            })
-                            .collect(),
-                    ),
+                .filter_map(|t| {
+                    Some(DocumentSymbol {
+                        name: t.identifier.name.clone(),
+                        detail: None,
+                        kind: SymbolKind::CLASS,
+                        tags: None,
+                        deprecated: None,
+                        range: self.span_to_range(&uri, t.span.as_ref()?.range()),
+                        selection_range: self
+                            .span_to_range(&uri, t.identifier.span.as_ref()?.range()),
+                        children: Some(
+                            t.relations
+                                .iter()
+                                .filter_map(|r| {
+                                    Some(DocumentSymbol {
+                                        name: r.identifier.name.clone(),
+                                        detail: None,
+                                        kind: SymbolKind::METHOD,
+                                        tags: None,
+                                        deprecated: None,
+                                        range: self.span_to_range(&uri, r.span.as_ref()?.range()),
+                                        selection_range: self.span_to_range(
+                                            &uri,
+                                            r.identifier.span.as_ref()?.range(),
+                                        ),
+                                        children: None,
+                                    })
+                                })
+                                .collect(),
+                        ),
+                    })
                 })
                 .collect(),
         );
         Ok(Some(res))
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let actions = params
+            .context
+            .diagnostics
+            .into_iter()
+            .flat_map(|diagnostic| {
+                let fixes: Vec<Fix> = diagnostic
+                    .data
+                    .clone()
+                    .and_then(|data| serde_json::from_value(data).ok())
+                    .unwrap_or_default();
+                let uri = uri.clone();
+                fixes.into_iter().map(move |fix| {
+                    CodeActionOrCommand::CodeAction(CodeAction {
+                        title: fix.title,
+                        kind: Some(CodeActionKind::QUICKFIX),
+                        diagnostics: Some(vec![diagnostic.clone()]),
+                        edit: Some(WorkspaceEdit {
+                            changes: Some(HashMap::from([(uri.clone(), fix.edits)])),
+                            ..Default::default()
+                        }),
+                        is_preferred: Some(fix.preferred),
+                        ..Default::default()
+                    })
+                })
+            })
+            .collect();
+        Ok(Some(actions))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let text = match self.rope_map.get(&uri.to_string()) {
+            Some(rope_ref) => match rope_ref.as_ref() {
+                Some(rope) => rope.to_string(),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        // Documents which don't parse are left untouched
+        let formatted = match format_model(&text) {
+            Ok(formatted) => formatted,
+            Err(_) => return Ok(None),
+        };
+        Ok(Some(vec![TextEdit {
+            range: self.span_to_range(&uri, 0..text.chars().count()),
+            new_text: formatted,
+        }]))
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
     ) -> Result<Option<SemanticTokensResult>> {
-        let tokens_ref = self
-            .token_map
-            .get(&params.text_document.uri.to_string())
-            .unwrap();
+        let tokens_ref = match self.token_map.get(&params.text_document.uri.to_string()) {
+            Some(tokens_ref) => tokens_ref,
+            None => return Ok(None),
+        };
         let tokens = match tokens_ref.as_ref() {
             Some(t) => t,
             None => return Ok(None),
         };
 
-        let rope_ref = self
-            .rope_map
-            .get(&params.text_document.uri.to_string())
-            .unwrap();
-        let rope = rope_ref.as_ref().unwrap();
+        let rope_ref = match self.rope_map.get(&params.text_document.uri.to_string()) {
+            Some(rope_ref) => rope_ref,
+            None => return Ok(None),
+        };
+        let rope = match rope_ref.as_ref() {
+            Some(rope) => rope,
+            None => return Ok(None),
+        };
 
         let mut prev_line: usize = 0;
         let mut prev_char: usize = 0;
@@ -215,28 +523,46 @@ impl LanguageServer for Backend {
             result_id: None,
             data: tokens
                 .iter()
-                .filter(|(t, _)| !(t == &Token::OpenParenthesis || t == &Token::CloseParenthesis))
+                .filter(|(t, _)| match t {
+                    Token::OpenParenthesis
+                    | Token::CloseParenthesis
+                    | Token::OpenBracket
+                    | Token::CloseBracket
+                    | Token::Comma
+                    | Token::LessThan
+                    | Token::GreaterThan => false,
+                    // Semantic tokens can't span multiple lines
+                    Token::Expression(expression) => !expression.contains('\n'),
+                    _ => true,
+                })
                 .map(|(t, r)| {
-                    let line = rope.char_to_line(r.start);
+                    let position = char_to_position(rope, r.start);
+                    let line = position.line as usize;
                     let delta_line = line - prev_line;
                     prev_line = line;
-                    let mut delta_start = r.start - rope.line_to_char(line);
-                    let len = r.len();
+                    let mut delta_start = position.character as usize;
+                    let len = rope.char_to_utf16_cu(r.end) - rope.char_to_utf16_cu(r.start);
                     if delta_line == 0 {
                         let char = delta_start;
-                        delta_start = delta_start - prev_char;
+                        delta_start -= prev_char;
                         prev_char = char;
                     } else {
                         prev_char = delta_start;
                     }
-                    return SemanticToken {
+                    SemanticToken {
                         delta_line: delta_line as u32,
                         delta_start: delta_start as u32,
                         token_type: match t {
+                            Token::Model => 0,
+                            Token::Schema => 0,
+                            Token::Module => 0,
+                            Token::Extend => 0,
                             Token::Type => 0,
                             Token::Define => 0,
                             Token::Relations => 0,
                             Token::As => 0,
+                            Token::Condition => 0,
+                            Token::With => 0,
                             Token::And => 1,
                             Token::Or => 1,
                             Token::From => 1,
@@ -244,12 +570,23 @@ impl LanguageServer for Backend {
                             Token::Not => 1,
                             Token::OpenParenthesis => 1,
                             Token::CloseParenthesis => 1,
+                            Token::OpenBracket => 1,
+                            Token::CloseBracket => 1,
+                            Token::Comma => 1,
+                            Token::Hash => 1,
+                            Token::Colon => 1,
+                            Token::Asterisk => 1,
+                            Token::LessThan => 1,
+                            Token::GreaterThan => 1,
                             Token::Identifier(_) => 2,
+                            Token::Version(_) => 4,
+                            Token::Expression(_) => 5,
+                            Token::Comment(_) => 6,
                             Token::SelfRef => 3,
                         },
                         length: len as u32,
                         ..Default::default()
-                    };
+                    }
                 })
                 .collect(),
         });
@@ -259,62 +596,289 @@ impl LanguageServer for Backend {
 }
 
 impl Backend {
-    async fn on_change(&self, uri: &Url, text: String) {
-        let res = parse_model(&text);
-        match res {
-            Ok((model, tokens)) => {
-                let check = check_model(&model);
-                match check {
-                    Ok(_) => {
-                        self.diagnostics_map.insert(uri.to_string(), None);
+    async fn on_change(&self, uri: &Url, rope: Rope) {
+        let model = self.update_document(uri, rope);
+        // Files of a modular model are only checked along with the other
+        // files of the model
+        if let Some((manifest_path, manifest)) = self.load_manifest(uri).await {
+            self.check_workspace(&manifest_path, &manifest).await;
+            return;
+        }
+        self.module_model_map.remove(&uri.to_string());
+        let diagnostics = match &model {
+            Some(model) => {
+                let config = self.load_config(uri).await;
+                Some(check_model_with_config(model, &config))
+            }
+            None => None,
+        };
+        let mut source_map = SourceMap::default();
+        if let Some(rope) = self
+            .rope_map
+            .get(&uri.to_string())
+            .and_then(|rope_ref| rope_ref.clone())
+        {
+            source_map.add_file(uri.to_string(), rope.to_string());
+        }
+        self.diagnostics_map.insert(uri.to_string(), diagnostics);
+        self.source_map_map
+            .insert(uri.to_string(), Arc::new(source_map));
+        self.publish_diagnostics(uri, model.as_ref()).await;
+    }
+
+    /// Parses the document and stores its source, tokens, model and syntax
+    /// errors. Broken documents keep the part of the model which could be
+    /// recovered, so symbols, hover and completion keep working while
+    /// editing. Returns the model if the document has no syntax errors, only
+    /// complete models get checked.
+    fn update_document(&self, uri: &Url, rope: Rope) -> Option<AuthorizationModel> {
+        let (model, tokens, errors) = parse_model_recovery(&rope.to_string());
+        self.rope_map.insert(uri.to_string(), Some(rope));
+        self.token_map.insert(uri.to_string(), Some(tokens));
+        self.syntax_errors_map.insert(
+            uri.to_string(),
+            errors.as_ref().map(|e| e.messages()).unwrap_or_default(),
+        );
+        self.model_map.insert(uri.to_string(), model.clone());
+        model.filter(|_| errors.is_none())
+    }
+
+    /// Loads the manifest of the modular model the document is a file of,
+    /// returns it along with its path.
+    async fn load_manifest(&self, uri: &Url) -> Option<(PathBuf, Manifest)> {
+        let path = uri.to_file_path().ok()?;
+        let manifest_path = find_manifest(&path)?;
+        let manifest = match Manifest::load(&manifest_path) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                self.client
+                    .show_message(MessageType::WARNING, err.to_string())
+                    .await;
+                return None;
+            }
+        };
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        manifest
+            .files(&manifest_path)
+            .into_iter()
+            .any(|file| fs::canonicalize(&file).unwrap_or(file) == path)
+            .then_some((manifest_path, manifest))
+    }
+
+    /// Checks the files of the modular model together and publishes the
+    /// diagnostics of every one of them. Files which aren't open are read
+    /// from disk the first time the model is checked.
+    async fn check_workspace(&self, manifest_path: &Path, manifest: &Manifest) {
+        let mut uris = Vec::new();
+        let mut source_map = SourceMap::default();
+        for path in manifest.files(manifest_path) {
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            let uri = match Url::from_file_path(&path) {
+                Ok(uri) => uri,
+                Err(_) => return,
+            };
+            let loaded = self
+                .rope_map
+                .get(&uri.to_string())
+                .and_then(|rope_ref| rope_ref.clone());
+            let rope = match loaded {
+                Some(rope) => rope,
+                None => match fs::read_to_string(&path) {
+                    Ok(src) => {
+                        let rope = Rope::from_str(&src);
+                        self.update_document(&uri, rope.clone());
+                        rope
                     }
-                    Err(errors) => {
-                        self.diagnostics_map.insert(uri.to_string(), Some(errors));
+                    Err(err) => {
+                        self.client
+                            .show_message(
+                                MessageType::WARNING,
+                                format!("Failed to read module file {}: {}", path.display(), err),
+                            )
+                            .await;
+                        return;
                     }
-                };
-                self.rope_map
-                    .insert(uri.to_string(), Some(Rope::from_str(&text)));
-                self.model_map.insert(uri.to_string(), Some(model));
-                self.token_map.insert(uri.to_string(), Some(tokens));
-                self.publish_diagnostics(uri).await;
-            }
-            Err(_) => {
-                if !self.model_map.contains_key(&uri.to_string()) {
-                    self.rope_map.insert(uri.to_string(), None);
-                    self.model_map.insert(uri.to_string(), None);
-                    self.token_map.insert(uri.to_string(), None);
-                };
+                },
+            };
+            source_map.add_file(uri.to_string(), rope.to_string());
+            uris.push(uri);
+        }
+
+        // The model is only checked if every file parses, findings are
+        // published on the file they point into
+        let mut findings: Option<Vec<Vec<(ModelError, Severity)>>> = None;
+        let mut module_model = None;
+        if let Ok(model) = parse_modules(&manifest.schema, &source_map) {
+            let config = match Config::load_for(manifest_path) {
+                Ok(config) => config,
+                Err(err) => {
+                    self.client
+                        .show_message(MessageType::WARNING, err.to_string())
+                        .await;
+                    Config::default()
+                }
+            };
+            let mut by_file: Vec<Vec<(ModelError, Severity)>> =
+                uris.iter().map(|_| Vec::new()).collect();
+            check_model_with_config(&model, &config)
+                .into_iter()
+                .for_each(|(e, severity)| by_file[e.get_span().file].push((e, severity)));
+            findings = Some(by_file);
+            module_model = Some(Arc::new(model));
+        }
+        let source_map = Arc::new(source_map);
+        for (file, uri) in uris.iter().enumerate() {
+            self.source_map_map
+                .insert(uri.to_string(), source_map.clone());
+            if let Some(model) = &module_model {
+                self.module_model_map
+                    .insert(uri.to_string(), (model.clone(), file));
+            } else {
+                self.module_model_map.remove(&uri.to_string());
             }
+            self.diagnostics_map.insert(
+                uri.to_string(),
+                findings
+                    .as_mut()
+                    .map(|by_file| std::mem::take(&mut by_file[file])),
+            );
+            // Fixes are only offered for models of a single file, as they
+            // can't edit the other files
+            self.publish_diagnostics(uri, None).await;
+        }
+    }
+
+    /// Loads the `.openfgarc` config applying to the document, falls back to
+    /// the default config if it can't be read.
+    async fn load_config(&self, uri: &Url) -> Config {
+        let path = match uri.to_file_path() {
+            Ok(path) => path,
+            Err(_) => return Config::default(),
         };
+        match Config::load_for(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                self.client
+                    .show_message(MessageType::WARNING, err.to_string())
+                    .await;
+                Config::default()
+            }
+        }
     }
 
-    async fn publish_diagnostics(&self, uri: &Url) {
-        let diagnostics = self
-            .diagnostics_map
+    /// Publishes the syntax errors and findings of the document, the fixes of
+    /// the findings are computed on the given model.
+    async fn publish_diagnostics(&self, uri: &Url, model: Option<&AuthorizationModel>) {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .syntax_errors_map
             .get(&uri.to_string())
-            .unwrap()
-            .as_ref()
-            .unwrap_or(&Vec::new())
-            .iter()
-            .map(|e| self.map_model_error_to_diagnostic(uri, e))
-            .collect();
+            .map(|errors| {
+                errors
+                    .iter()
+                    .map(|(span, message)| Diagnostic {
+                        range: self.span_to_range(uri, span.clone()),
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: Some(NumberOrString::Number(3)),
+                        source: Some(String::from("openfga")),
+                        message: message.clone(),
+                        ..Default::default()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let source_map = self
+            .source_map_map
+            .get(&uri.to_string())
+            .map(|source_map| source_map.clone())
+            .unwrap_or_default();
+        if let Some(findings) = self.diagnostics_map.get(&uri.to_string()) {
+            diagnostics.extend(findings.iter().flatten().map(|(e, severity)| {
+                self.map_model_error_to_diagnostic(uri, &source_map, model, e, *severity)
+            }));
+        }
         self.client
             .publish_diagnostics(uri.clone(), diagnostics, None)
             .await;
     }
 
-    fn map_model_error_to_diagnostic(&self, uri: &Url, error: &ModelError) -> Diagnostic {
-        return Diagnostic {
-            range: self.span_to_range(&uri, error.get_span()),
-            severity: Some(DiagnosticSeverity::ERROR),
+    /// Maps the finding to a diagnostic of the document, the spans of the
+    /// finding point into the files of the source map.
+    fn map_model_error_to_diagnostic(
+        &self,
+        uri: &Url,
+        source_map: &SourceMap,
+        model: Option<&AuthorizationModel>,
+        error: &ModelError,
+        severity: Severity,
+    ) -> Diagnostic {
+        let fixes = match (model, self.rope_map.get(&uri.to_string())) {
+            (Some(model), Some(rope_ref)) => rope_ref
+                .as_ref()
+                .map(|rope| fixes(rope, model, error))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let similar = error.get_similar_names();
+        let message = if similar.is_empty() {
+            format!("{}", error)
+        } else {
+            format!("{} Did you mean {}?", error, similar.join(", "))
+        };
+        // The first definition of a duplicate and the definitions of the
+        // similar names are linked, so the client can jump to them from the
+        // diagnostic, even if they are in another file of the model
+        let related_information: Vec<DiagnosticRelatedInformation> = first_definition(error)
+            .map(|identifier| (identifier, "first defined here"))
+            .into_iter()
+            .chain(
+                model
+                    .map(|model| similar_name_definitions(model, error))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|identifier| (identifier, "defined here")),
+            )
+            .filter_map(|(identifier, message)| {
+                Some(DiagnosticRelatedInformation {
+                    location: self.span_location(source_map, identifier.span.as_ref()?)?,
+                    message: format!("{} is {}", identifier.name, message),
+                })
+            })
+            .collect();
+        let range = match self.span_location(source_map, &error.get_span()) {
+            Some(location) => location.range,
+            None => self.span_to_range(uri, error.get_span().range()),
+        };
+        Diagnostic {
+            range,
+            severity: Some(match severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+                Severity::Info => DiagnosticSeverity::INFORMATION,
+            }),
             code: Some(NumberOrString::Number(error.get_code() as i32)),
             code_description: None,
             source: Some(String::from("openfga")),
-            message: format!("{}", error),
-            related_information: None,
+            message,
+            related_information: if related_information.is_empty() {
+                None
+            } else {
+                Some(related_information)
+            },
             tags: None,
-            data: None,
-        };
+            data: if fixes.is_empty() {
+                None
+            } else {
+                serde_json::to_value(fixes).ok()
+            },
+        }
+    }
+
+    /// Resolves the span to the location in the document of the source map it
+    /// points into. Returns `None` if the file isn't part of the map.
+    fn span_location(&self, source_map: &SourceMap, span: &Span) -> Option<Location> {
+        let uri = Url::parse(&source_map.file(span.file)?.name).ok()?;
+        let range = self.span_to_range(&uri, span.range());
+        Some(Location::new(uri, range))
     }
 
     fn span_to_range(&self, uri: &Url, span: OpsRange<usize>) -> Range {
@@ -324,18 +888,783 @@ impl Backend {
         }
     }
 
+    /// Runs the lookup on the symbol at the position, returning the locations
+    /// of the identifiers it finds. Returns `None` if there is no symbol.
+    fn lookup_symbol(
+        &self,
+        uri: &Url,
+        position: Position,
+        lookup: impl Fn(&AuthorizationModel, &Symbol) -> Vec<Identifier>,
+    ) -> Option<Vec<Location>> {
+        self.with_symbol(uri, position, |model, symbol, locate| {
+            lookup(model, symbol)
+                .iter()
+                .filter_map(|identifier| locate(identifier.span.as_ref()?))
+                .collect()
+        })
+    }
+
+    /// Calls the function with the symbol at the position of the document,
+    /// along with the model it belongs to and a function locating the spans of
+    /// that model. Files of a modular model are looked up in the whole model,
+    /// spans of it are resolved to their file through its source map.
+    fn with_symbol<T>(
+        &self,
+        uri: &Url,
+        position: Position,
+        f: impl FnOnce(&AuthorizationModel, &Symbol, &dyn Fn(&Span) -> Option<Location>) -> T,
+    ) -> Option<T> {
+        let offset = self.position_to_char(uri, position)?;
+        let module = self
+            .module_model_map
+            .get(&uri.to_string())
+            .map(|module| module.clone());
+        if let Some((model, file)) = module {
+            let source_map = self.source_map_map.get(&uri.to_string())?.clone();
+            // The relations of `extend type` definitions are merged into the
+            // type they extend, their name is looked up as that type
+            let extended;
+            let symbol = match symbol_at_in_file(&model, file, offset) {
+                Some(symbol) => symbol,
+                None => {
+                    extended = self.extended_type_at(uri, file, offset)?;
+                    Symbol::Type(&extended)
+                }
+            };
+            return Some(f(&model, &symbol, &|span| {
+                self.span_location(&source_map, span)
+            }));
+        }
+        let model_ref = self.model_map.get(&uri.to_string())?;
+        let model = model_ref.as_ref()?;
+        let symbol = symbol_at(model, offset)?;
+        Some(f(model, &symbol, &|span| {
+            Some(Location::new(
+                uri.clone(),
+                self.span_to_range(uri, span.range()),
+            ))
+        }))
+    }
+
+    /// Returns the name of the `extend type` definition of the document at the
+    /// offset, with its span in the given file of the modular model.
+    fn extended_type_at(&self, uri: &Url, file: FileId, offset: usize) -> Option<Identifier> {
+        let model_ref = self.model_map.get(&uri.to_string())?;
+        let identifier = &model_ref
+            .as_ref()?
+            .types
+            .iter()
+            .find(|t| {
+                t.extension
+                    && t.identifier
+                        .span
+                        .as_ref()
+                        .is_some_and(|span| span.start <= offset && offset <= span.end)
+            })?
+            .identifier;
+        let span = identifier.span.as_ref()?;
+        Some(Identifier {
+            name: identifier.name.clone(),
+            span: Some(Span::new(file, span.range())),
+        })
+    }
+
+    fn position_to_char(&self, uri: &Url, position: Position) -> Option<usize> {
+        let rope_ref = self.rope_map.get(&uri.to_string())?;
+        position_to_char(rope_ref.as_ref()?, position)
+    }
+
+    /// Returns the position of the character in the document, documents which
+    /// aren't loaded only have the start position.
     fn char_to_position(&self, uri: &Url, char: usize) -> Position {
-        let rope_ref = self.rope_map.get(&uri.to_string()).unwrap();
-        let rope = rope_ref.as_ref().unwrap();
-        let line = rope.char_to_line(char);
-        let start = rope.line_to_char(line);
-        Position {
-            line: line as u32,
-            character: (char - start) as u32,
+        self.rope_map
+            .get(&uri.to_string())
+            .and_then(|rope_ref| rope_ref.as_ref().map(|rope| char_to_position(rope, char)))
+            .unwrap_or_default()
+    }
+}
+
+/// Converts the position, whose character counts UTF-16 code units as
+/// required by the protocol, to a character offset. Positions behind the end
+/// of their line are moved to its end.
+fn position_to_char(rope: &Rope, position: Position) -> Option<usize> {
+    let line = position.line as usize;
+    if line >= rope.len_lines() {
+        return None;
+    }
+    let start = rope.line_to_char(line);
+    let end = start + rope.line(line).len_chars();
+    let code_unit = rope.char_to_utf16_cu(start) + position.character as usize;
+    Some(
+        rope.utf16_cu_to_char(code_unit.min(rope.len_utf16_cu()))
+            .min(end),
+    )
+}
+
+/// Converts the character offset to a position, offsets behind the end of
+/// the document like the one of an end of input error are moved to its end.
+fn char_to_position(rope: &Rope, char: usize) -> Position {
+    let char = char.min(rope.len_chars());
+    let line = rope.char_to_line(char);
+    let start = rope.line_to_char(line);
+    Position {
+        line: line as u32,
+        character: (rope.char_to_utf16_cu(char) - rope.char_to_utf16_cu(start)) as u32,
+    }
+}
+
+/// Returns the range of the character offsets in the document.
+fn span_to_range(rope: &Rope, span: OpsRange<usize>) -> Range {
+    Range {
+        start: char_to_position(rope, span.start),
+        end: char_to_position(rope, span.end),
+    }
+}
+
+/// Returns the fixes offered for the error.
+fn fixes(rope: &Rope, model: &AuthorizationModel, error: &ModelError) -> Vec<Fix> {
+    // Fixes are left out if an identifier to edit has no span
+    let replace = |identifier: &Identifier, name: &str| {
+        Some(TextEdit {
+            range: span_to_range(rope, identifier.span.as_ref()?.range()),
+            new_text: name.to_string(),
+        })
+    };
+    match error {
+        ModelError::DuplicateTypeName { type1: _, type2 } => {
+            let name = (2..)
+                .map(|i| format!("{}_{}", type2.identifier.name, i))
+                .find(|name| !model.type_exists(name))
+                .unwrap();
+            replace(&type2.identifier, &name)
+                .map(|edit| Fix {
+                    title: format!("Rename duplicate type to {}", name),
+                    edits: vec![edit],
+                    preferred: true,
+                })
+                .into_iter()
+                .collect()
+        }
+        ModelError::DuplicateRelationName {
+            relation1: _,
+            relation2,
+            target_type: _,
+        } => {
+            // The whole lines of the duplicate are removed, along with the
+            // comments documenting it
+            let span = match relation2.span.clone() {
+                Some(span) => span,
+                None => return Vec::new(),
+            };
+            let start = relation2
+                .comments
+                .leading
+                .first()
+                .and_then(|c| c.span.clone())
+                .map_or(span.start, |comment| comment.start);
+            let start = char_to_position(rope, start);
+            let end = char_to_position(rope, span.end);
+            let range = if (end.line as usize) + 1 < rope.len_lines() {
+                Range {
+                    start: Position::new(start.line, 0),
+                    end: Position::new(end.line + 1, 0),
+                }
+            } else {
+                // The duplicate ends the document without a line break, the
+                // one ending the line before it is removed instead
+                let line_start = rope.line_to_char(start.line as usize);
+                Range {
+                    start: char_to_position(rope, line_start.saturating_sub(1)),
+                    end: char_to_position(rope, rope.len_chars()),
+                }
+            };
+            vec![Fix {
+                title: format!(
+                    "Remove duplicate definition of relation {}",
+                    relation2.identifier.name
+                ),
+                edits: vec![TextEdit {
+                    range,
+                    new_text: String::new(),
+                }],
+                preferred: true,
+            }]
+        }
+        ModelError::UnknownRelation {
+            relation_identifier,
+            access: _,
+            relation,
+            target_type,
+            suggestions: _,
+        } => {
+            let mut fixes = similar_name_fixes(rope, error);
+            fixes.extend(create_relation_fix(
+                rope,
+                model,
+                &relation_identifier.name,
+                relation,
+                target_type,
+            ));
+            fixes
+        }
+        _ if !error.get_similar_names().is_empty() => similar_name_fixes(rope, error),
+        _ => {
+            // Naming lints suggest a new name, unless it collides with an
+            // existing one
+            let suggestion = match error.get_suggestion() {
+                Some(suggestion) => suggestion,
+                None => return Vec::new(),
+            };
+            let identifiers = match renamed_symbol(error)
+                .and_then(|symbol| rename_symbol(model, &symbol, suggestion).ok())
+            {
+                Some(identifiers) => identifiers,
+                None => return Vec::new(),
+            };
+            let edits = match identifiers
+                .iter()
+                .map(|identifier| replace(identifier, suggestion))
+                .collect()
+            {
+                Some(edits) => edits,
+                None => return Vec::new(),
+            };
+            vec![Fix {
+                title: format!("Rename to {}", suggestion),
+                edits,
+                preferred: true,
+            }]
+        }
+    }
+}
+
+/// Returns the fixes replacing the unknown name of the error by one of the
+/// existing names close to it.
+fn similar_name_fixes(rope: &Rope, error: &ModelError) -> Vec<Fix> {
+    let range = span_to_range(rope, error.get_span().range());
+    error
+        .get_similar_names()
+        .iter()
+        .enumerate()
+        .map(|(i, name)| Fix {
+            title: format!("Did you mean {}?", name),
+            edits: vec![TextEdit {
+                range,
+                new_text: name.clone(),
+            }],
+            preferred: i == 0,
+        })
+        .collect()
+}
+
+/// Returns a fix adding the relation below the last relation of the type.
+/// In schema 1.1 it is directly assignable to the types the referencing
+/// relation allows, or else to the types without relations, which usually
+/// are users.
+fn create_relation_fix(
+    rope: &Rope,
+    model: &AuthorizationModel,
+    name: &str,
+    relation: &Relation,
+    target_type: &Type,
+) -> Option<Fix> {
+    let last = target_type.relations.last()?.span.clone()?;
+    let access = if model.is_legacy_schema() {
+        "self".to_string()
+    } else {
+        let mut types: Vec<String> = relation
+            .type_restrictions()
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+        if types.is_empty() {
+            types = model
+                .types
+                .iter()
+                .filter(|t| t.relations.is_empty())
+                .map(|t| t.identifier.name.clone())
+                .collect();
+        }
+        if types.is_empty() {
+            return None;
+        }
+        let mut seen = HashSet::new();
+        types.retain(|t| seen.insert(t.clone()));
+        format!("[{}]", types.join(", "))
+    };
+    let start = char_to_position(rope, last.start);
+    let end = char_to_position(rope, last.end);
+    let definition = if model.is_legacy_schema() {
+        format!("define {} as {}", name, access)
+    } else {
+        format!("define {}: {}", name, access)
+    };
+    let indentation = " ".repeat(start.character as usize);
+    // The last relation can end the document without a line break
+    let (position, new_text) = if (end.line as usize) + 1 < rope.len_lines() {
+        (
+            Position::new(end.line + 1, 0),
+            format!("{}{}\n", indentation, definition),
+        )
+    } else {
+        (
+            char_to_position(rope, rope.len_chars()),
+            format!("\n{}{}", indentation, definition),
+        )
+    };
+    Some(Fix {
+        title: format!(
+            "Create relation {} on type {}",
+            name, target_type.identifier.name
+        ),
+        edits: vec![TextEdit {
+            range: Range {
+                start: position,
+                end: position,
+            },
+            new_text,
+        }],
+        preferred: false,
+    })
+}
+
+/// Returns the definition of the relation as written in the model.
+fn relation_definition(relation: &Relation, legacy: bool) -> String {
+    if legacy {
+        format!("define {} as {}", relation.identifier.name, relation.access)
+    } else {
+        format!("define {}: {}", relation.identifier.name, relation.access)
+    }
+}
+
+/// Appends the documentation written in the leading comments of a definition.
+fn with_documentation(value: String, comments: &Comments) -> String {
+    match comments.documentation() {
+        Some(documentation) => format!("{}\n\n---\n\n{}", value, documentation),
+        None => value,
+    }
+}
+
+/// Returns the markdown shown when hovering the symbol. Types extended by
+/// other files of a modular model are shown with the relations of every file.
+fn hover_contents(model: &AuthorizationModel, symbol: &Symbol) -> Option<String> {
+    let mut resolved = model.clone();
+    resolved.resolve_extensions();
+    let model = &resolved;
+    let legacy = model.is_legacy_schema();
+    let find_type = |name: &str| model.types.iter().find(|t| t.identifier.name == name);
+    match symbol {
+        Symbol::Type(identifier) => {
+            let t = find_type(&identifier.name)?;
+            let mut value = format!("```openfga\ntype {}\n", t.identifier.name);
+            if !t.relations.is_empty() {
+                value += "  relations\n";
+            }
+            for relation in &t.relations {
+                value += &format!("    {}\n", relation_definition(relation, legacy));
+            }
+            value += "```";
+            Some(with_documentation(value, &t.comments))
+        }
+        Symbol::Relation {
+            identifier,
+            type_name,
+        } => {
+            let relation = find_type(type_name)?
+                .relations
+                .iter()
+                .find(|r| r.identifier.name == identifier.name)?;
+            let value = format!(
+                "```openfga\n{}\n```\nRelation of `{}`",
+                relation_definition(relation, legacy),
+                type_name
+            );
+            Some(with_documentation(value, &relation.comments))
+        }
+        Symbol::Tupleset { identifier, rtype } => {
+            let rtype = find_type(&rtype.identifier.name)?;
+            let relation = rtype
+                .relations
+                .iter()
+                .find(|r| r.identifier.name == identifier.name)?;
+            let types = tupleset_types(model, rtype, &identifier.name);
+            let value = format!(
+                "```openfga\n{}\n```\n`{}` can point to {}",
+                relation_definition(relation, legacy),
+                identifier.name,
+                types
+                    .iter()
+                    .map(|t| format!("`{}`", t))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            Some(with_documentation(value, &relation.comments))
+        }
+        Symbol::TuplesetRelation {
+            identifier,
+            tupleset,
+            rtype,
+        } => {
+            let rtype = find_type(&rtype.identifier.name)?;
+            let mut value = format!("`{} from {}` resolves to\n", identifier.name, tupleset.name);
+            for type_name in tupleset_types(model, rtype, &tupleset.name) {
+                let relation = find_type(&type_name).and_then(|t| {
+                    t.relations
+                        .iter()
+                        .find(|r| r.identifier.name == identifier.name)
+                });
+                value += &match relation {
+                    Some(relation) => format!(
+                        "- `{}#{}`: `{}`\n",
+                        type_name, identifier.name, relation.access
+                    ),
+                    None => format!("- `{}`: no relation `{}`\n", type_name, identifier.name),
+                };
+            }
+            Some(value)
+        }
+        Symbol::Condition(identifier) => {
+            let condition = model
+                .conditions
+                .iter()
+                .find(|c| c.identifier.name == identifier.name)?;
+            let value = format!("```openfga\n{}\n```", condition);
+            Some(with_documentation(value, &condition.comments))
         }
     }
 }
 
+/// Returns true for tokens lexed from a word, which are still being typed if
+/// the cursor is right behind them.
+fn is_word(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Model
+            | Token::Schema
+            | Token::Module
+            | Token::Extend
+            | Token::Type
+            | Token::Relations
+            | Token::SelfRef
+            | Token::Define
+            | Token::And
+            | Token::Or
+            | Token::From
+            | Token::As
+            | Token::But
+            | Token::Not
+            | Token::With
+            | Token::Condition
+            | Token::Identifier(_)
+    )
+}
+
+fn keyword_items(keywords: &[&str]) -> Vec<CompletionItem> {
+    keywords
+        .iter()
+        .map(|keyword| CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn relation_items<'a>(relations: impl Iterator<Item = &'a Relation>) -> Vec<CompletionItem> {
+    relations
+        .map(|relation| CompletionItem {
+            label: relation.identifier.name.clone(),
+            kind: Some(CompletionItemKind::METHOD),
+            detail: Some(relation.access.to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Returns the completions at the character offset, based on the tokens
+/// before it. Works on the partial model recovered from broken documents.
+fn completion_items(
+    model: Option<&AuthorizationModel>,
+    tokens: &[Spanned<Token>],
+    text: &str,
+    offset: usize,
+) -> Vec<CompletionItem> {
+    // Nothing is completed inside of comments and condition expressions, the
+    // cursor right behind a comment is still on its line
+    if tokens.iter().any(|(token, span)| match token {
+        Token::Comment(_) => span.start < offset && offset <= span.end,
+        Token::Expression(_) => span.start < offset && offset < span.end,
+        _ => false,
+    }) {
+        return Vec::new();
+    }
+    let before: Vec<&Spanned<Token>> = tokens
+        .iter()
+        .filter(|(token, span)| {
+            !token.is_trivia() && (span.end < offset || (span.end == offset && !is_word(token)))
+        })
+        .collect();
+    let token_at = |back: usize| before.len().checked_sub(back + 1).map(|i| &before[i].0);
+    let (previous, previous_span) = match before.last() {
+        Some((token, span)) => (token, span),
+        None => return keyword_items(&["model", "module", "type", "extend", "condition"]),
+    };
+    let new_line = text
+        .chars()
+        .skip(previous_span.end)
+        .take(offset.saturating_sub(previous_span.end))
+        .any(|c| c == '\n');
+
+    // Type and relation the cursor is in, the relation only counts once its
+    // access started
+    let mut current_type = None;
+    let mut current_relation = None;
+    let mut in_access = false;
+    let mut brackets = 0;
+    for (i, (token, _)) in before.iter().enumerate() {
+        let next = before.get(i + 1).map(|(t, _)| t);
+        match (token, next) {
+            (Token::Type, Some(Token::Identifier(name))) => {
+                current_type = Some(name.as_str());
+                current_relation = None;
+                in_access = false;
+            }
+            (Token::Condition, _) => {
+                current_type = None;
+                current_relation = None;
+                in_access = false;
+            }
+            (Token::Define, next) => {
+                current_relation = match next {
+                    Some(Token::Identifier(name)) => Some(name.as_str()),
+                    _ => None,
+                };
+                in_access = false;
+                brackets = 0;
+            }
+            (Token::As | Token::Colon, _) if brackets == 0 && current_relation.is_some() => {
+                in_access = true
+            }
+            (Token::OpenBracket, _) => brackets += 1,
+            (Token::CloseBracket, _) => brackets -= 1,
+            _ => (),
+        }
+    }
+    let legacy = model.is_some_and(|m| m.is_legacy_schema());
+    let rtype = model.and_then(|m| {
+        m.types
+            .iter()
+            .find(|t| Some(t.identifier.name.as_str()) == current_type)
+    });
+    let relations = || {
+        rtype
+            .into_iter()
+            .flat_map(|t| t.relations.iter())
+            .filter(|r| Some(r.identifier.name.as_str()) != current_relation)
+    };
+    let definition_keywords: &[&str] = if current_type.is_some() {
+        &["define", "type", "extend", "condition"]
+    } else {
+        &["type", "extend", "condition"]
+    };
+
+    // Inside of the brackets of a direct access
+    if in_access && brackets > 0 {
+        return match previous {
+            Token::OpenBracket | Token::Comma => model
+                .into_iter()
+                .flat_map(|m| m.types.iter())
+                .map(|t| CompletionItem {
+                    label: t.identifier.name.clone(),
+                    kind: Some(CompletionItemKind::CLASS),
+                    ..Default::default()
+                })
+                .collect(),
+            Token::Hash => match token_at(1) {
+                Some(Token::Identifier(type_name)) => relation_items(
+                    model
+                        .into_iter()
+                        .flat_map(|m| m.types.iter())
+                        .filter(|t| &t.identifier.name == type_name)
+                        .flat_map(|t| t.relations.iter()),
+                ),
+                _ => Vec::new(),
+            },
+            Token::With => model
+                .into_iter()
+                .flat_map(|m| m.conditions.iter())
+                .map(|c| CompletionItem {
+                    label: c.identifier.name.clone(),
+                    kind: Some(CompletionItemKind::FUNCTION),
+                    ..Default::default()
+                })
+                .collect(),
+            Token::Identifier(_) | Token::Asterisk
+                if !legacy && !matches!(token_at(1), Some(Token::With)) =>
+            {
+                keyword_items(&["with"])
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    match previous {
+        Token::As | Token::Colon | Token::Or | Token::And | Token::Not | Token::OpenParenthesis
+            if in_access =>
+        {
+            let mut items = relation_items(relations());
+            if legacy {
+                items.extend(keyword_items(&["self"]));
+            }
+            items
+        }
+        Token::But if in_access => keyword_items(&["not"]),
+        Token::From if in_access => relation_items(relations().filter(|r| is_tupleset(r))),
+        Token::Identifier(_) if in_access => {
+            let mut keywords = vec!["or", "and", "but not"];
+            if !matches!(token_at(1), Some(Token::From)) {
+                keywords.push("from");
+            }
+            if new_line {
+                keywords.extend(definition_keywords);
+            }
+            keyword_items(&keywords)
+        }
+        Token::SelfRef | Token::CloseBracket | Token::CloseParenthesis if in_access => {
+            let mut keywords = vec!["or", "and", "but not"];
+            if new_line {
+                keywords.extend(definition_keywords);
+            }
+            keyword_items(&keywords)
+        }
+        Token::Identifier(_) => match token_at(1) {
+            Some(Token::Type) if new_line => {
+                keyword_items(&["relations", "type", "extend", "condition"])
+            }
+            Some(Token::Type) => keyword_items(&["relations"]),
+            Some(Token::Module) if new_line => keyword_items(&["type", "extend", "condition"]),
+            Some(Token::Define) if legacy => keyword_items(&["as"]),
+            _ => Vec::new(),
+        },
+        Token::Relations => keyword_items(&["define"]),
+        Token::Extend => keyword_items(&["type"]),
+        Token::Version(_) | Token::Expression(_) => keyword_items(&["type", "extend", "condition"]),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the identifier of the first definition of a duplicated name.
+fn first_definition(error: &ModelError) -> Option<&Identifier> {
+    match error {
+        ModelError::DuplicateTypeName { type1, type2: _ } => Some(&type1.identifier),
+        ModelError::DuplicateRelationName {
+            relation1,
+            relation2: _,
+            target_type: _,
+        } => Some(&relation1.identifier),
+        ModelError::DuplicateConditionName {
+            condition1,
+            condition2: _,
+        } => Some(&condition1.identifier),
+        _ => None,
+    }
+}
+
+/// Returns the identifiers defining the names similar to the unknown name of
+/// the error, in the order of the suggestions.
+fn similar_name_definitions<'a>(
+    model: &'a AuthorizationModel,
+    error: &ModelError,
+) -> Vec<&'a Identifier> {
+    let suggestions = error.get_similar_names();
+    let type_names: Vec<&str> = match error {
+        ModelError::UnknownRestrictionType {
+            restriction: _,
+            relation: _,
+            target_type: _,
+            suggestions: _,
+        } => {
+            return suggestions
+                .iter()
+                .filter_map(|name| model.types.iter().find(|t| t.identifier.name == *name))
+                .map(|t| &t.identifier)
+                .collect()
+        }
+        ModelError::UnknownRelation {
+            relation_identifier: _,
+            access: _,
+            relation: _,
+            target_type,
+            suggestions: _,
+        }
+        | ModelError::UnknownTupleset {
+            tupleset_identifier: _,
+            access: _,
+            relation: _,
+            target_type,
+            suggestions: _,
+        } => vec![&target_type.identifier.name],
+        ModelError::UnknownRestrictionRelation {
+            restriction,
+            relation: _,
+            target_type: _,
+            suggestions: _,
+        } => vec![&restriction.type_identifier.name],
+        ModelError::UnknownTuplesetRelation {
+            relation_identifier: _,
+            tupleset: _,
+            candidate_types,
+            access: _,
+            relation: _,
+            target_type: _,
+            suggestions: _,
+        } => candidate_types.iter().map(String::as_str).collect(),
+        _ => return Vec::new(),
+    };
+    let relations: Vec<&Relation> = model
+        .types
+        .iter()
+        .filter(|t| type_names.contains(&t.identifier.name.as_str()))
+        .flat_map(|t| &t.relations)
+        .collect();
+    suggestions
+        .iter()
+        .filter_map(|name| relations.iter().find(|r| r.identifier.name == *name))
+        .map(|r| &r.identifier)
+        .collect()
+}
+
+/// Returns the symbol a naming lint suggests to rename.
+fn renamed_symbol(error: &ModelError) -> Option<Symbol<'_>> {
+    match error {
+        ModelError::TypeNamingConvention {
+            target_type,
+            convention: _,
+            suggestion: _,
+        }
+        | ModelError::PluralTypeName {
+            target_type,
+            suggestion: _,
+        } => Some(Symbol::Type(&target_type.identifier)),
+        ModelError::RelationNamingConvention {
+            relation,
+            target_type,
+            convention: _,
+            suggestion: _,
+        }
+        | ModelError::MissingPermissionPrefix {
+            relation,
+            target_type,
+            prefix: _,
+            suggestion: _,
+        } => Some(Symbol::Relation {
+            identifier: &relation.identifier,
+            type_name: &target_type.identifier.name,
+        }),
+        _ => None,
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let stdin = tokio::io::stdin();
@@ -344,9 +1673,251 @@ async fn main() {
     let (service, socket) = LspService::new(|client| Backend {
         client,
         diagnostics_map: DashMap::new(),
+        source_map_map: DashMap::new(),
+        module_model_map: DashMap::new(),
+        syntax_errors_map: DashMap::new(),
         model_map: DashMap::new(),
         token_map: DashMap::new(),
         rope_map: DashMap::new(),
     });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user]
+type folder
+  relations
+    define owner: [user]
+type doc
+  relations
+    define parent: [folder]
+    define owner: [user, group#member]
+    define viewer: |
+condition in_hours(hour: int) {
+  hour > 9
+}
+";
+
+    /// Returns the labels of the completions at the `|` of the source.
+    fn completions(src: &str) -> Vec<String> {
+        let offset = src[..src.find('|').unwrap()].chars().count();
+        let text = src.replacen('|', "", 1);
+        let (model, tokens, _) = parse_model_recovery(&text);
+        completion_items(model.as_ref(), &tokens, &text, offset)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    #[test]
+    fn completes_by_cursor_context() {
+        for (access, expected) in [
+            ("|", vec!["parent", "owner"]),
+            ("owner or |", vec!["parent", "owner"]),
+            ("owner and |", vec!["parent", "owner"]),
+            ("owner but not |", vec!["parent", "owner"]),
+            ("owner but |", vec!["not"]),
+            ("owner from |", vec!["parent"]),
+            ("owner |", vec!["or", "and", "but not", "from"]),
+            ("owner from parent |", vec!["or", "and", "but not"]),
+            ("[|", vec!["user", "group", "folder", "doc"]),
+            ("[user, |", vec!["user", "group", "folder", "doc"]),
+            ("[group#|", vec!["member"]),
+            ("[user |", vec!["with"]),
+            ("[user with |", vec!["in_hours"]),
+            ("[user with |]", vec!["in_hours"]),
+            ("[user with in_hours |", vec![]),
+        ] {
+            let src = MODEL.replace('|', access);
+            assert_eq!(completions(&src), expected, "{access}");
+        }
+    }
+
+    #[test]
+    fn completes_documents_which_do_not_parse() {
+        // Nothing before the cursor
+        assert_eq!(
+            completions("|"),
+            vec!["model", "module", "type", "extend", "condition"]
+        );
+        // Broken relations don't hide the rest of the type
+        let src = "model
+  schema 1.1
+type user
+type doc
+  relations
+    define owner: [user,
+    define editor: [user]
+    define viewer: editor or |
+type folder
+";
+        assert_eq!(completions(src), vec!["editor"]);
+        // Without any model, keywords are still completed from the tokens
+        let text = "type doc\n  relations\n    define viewer: ]]] (\ntype folder\n  relations ";
+        let (_, tokens, errors) = parse_model_recovery(text);
+        assert!(errors.is_some());
+        let labels = |offset| {
+            completion_items(None, &tokens, text, offset)
+                .into_iter()
+                .map(|item| item.label)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(labels(text.len()), vec!["define"]);
+        assert_eq!(
+            labels("type doc\n  relations\n    define viewer: ".len()),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            completions("type doc\n  relations\n    define viewer: [|"),
+            vec!["doc"]
+        );
+        // Nothing is completed in comments and condition expressions
+        assert!(completions("type doc // define |\n").is_empty());
+        assert!(completions("condition c(x: int) {\n  x > |\n}\n").is_empty());
+    }
+
+    /// Returns the title of every fix of the errors of the source, along with
+    /// the source with its edits applied.
+    fn apply_fixes(src: &str) -> Vec<(String, String)> {
+        let (model, _) = openfga_model_dsl_parser::parse_model(src).unwrap();
+        let rope = Rope::from_str(src);
+        check_model_with_config(&model, &Config::default())
+            .iter()
+            .flat_map(|(error, _)| fixes(&rope, &model, error))
+            .map(|fix| {
+                let mut fixed = rope.clone();
+                // Edits are applied from the end, so their ranges stay valid
+                let mut edits = fix.edits;
+                edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+                for edit in edits {
+                    let start = position_to_char(&fixed, edit.range.start).unwrap();
+                    let end = position_to_char(&fixed, edit.range.end).unwrap();
+                    fixed.remove(start..end);
+                    fixed.insert(start, &edit.new_text);
+                }
+                (fix.title, fixed.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renames_duplicate_types() {
+        let src = "model\n  schema 1.1\ntype user\ntype user\ntype user_2\n";
+        assert_eq!(
+            apply_fixes(src),
+            vec![(
+                "Rename duplicate type to user_3".to_string(),
+                "model\n  schema 1.1\ntype user\ntype user_3\ntype user_2\n".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn removes_duplicate_relations_with_their_comments() {
+        let src = "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [user]
+    // Allowed to view
+    define viewer: [user] or
+      editor
+    define editor: [user]
+";
+        assert_eq!(
+            apply_fixes(src),
+            vec![(
+                "Remove duplicate definition of relation viewer".to_string(),
+                "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [user]
+    define editor: [user]
+"
+                .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn removes_duplicate_relations_ending_the_document() {
+        let src = "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user]\n    define viewer: [user]";
+        assert_eq!(
+            apply_fixes(src)[0].1,
+            "model\n  schema 1.1\ntype user\ntype doc\n  relations\n    define viewer: [user]"
+        );
+    }
+
+    #[test]
+    fn fixes_unknown_relations() {
+        let src = "model
+  schema 1.1
+type user
+type team
+type doc
+  relations
+    define viewer: [user, team]
+    define can_view: veiwer
+";
+        let fixed = |relation: &str| src.replacen("define can_view: veiwer", relation, 1);
+        assert_eq!(
+            apply_fixes(src),
+            vec![
+                (
+                    "Did you mean viewer?".to_string(),
+                    fixed("define can_view: viewer")
+                ),
+                (
+                    "Create relation veiwer on type doc".to_string(),
+                    fixed("define can_view: veiwer\n    define veiwer: [user, team]")
+                ),
+            ]
+        );
+        // Legacy models create a directly assignable relation
+        let legacy = "model
+  schema 1.0
+type user
+type doc
+  relations
+    define can_view as viewer
+";
+        assert_eq!(
+            apply_fixes(legacy.trim_end())[0].1,
+            legacy.to_string() + "    define viewer as self"
+        );
+        assert_eq!(
+            apply_fixes(legacy)[0],
+            (
+                "Create relation viewer on type doc".to_string(),
+                legacy.to_string() + "    define viewer as self\n"
+            )
+        );
+    }
+
+    #[test]
+    fn suggests_similar_types() {
+        let src = "model
+  schema 1.1
+type user
+type usrs
+type doc
+  relations
+    define viewer: [usr]
+";
+        let fixes = apply_fixes(src);
+        let titles: Vec<&str> = fixes.iter().map(|(title, _)| title.as_str()).collect();
+        assert_eq!(titles, vec!["Did you mean user?", "Did you mean usrs?"]);
+        assert_eq!(fixes[0].1, src.replacen("[usr]", "[user]", 1));
+    }
+}
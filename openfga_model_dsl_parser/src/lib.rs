@@ -1,13 +1,24 @@
 use std::{fmt, ops::Range};
 
-use chumsky::{prelude::*, stream::Stream};
-use openfga_common::{Access, AuthorizationModel, Identifier, Relation, Type};
+use chumsky::{error::SimpleReason, prelude::*, stream::Stream};
+use openfga_common::{
+    Access, AuthorizationModel, Comment, Comments, Condition, ConditionParameter, FileId,
+    Identifier, ParameterType, Relation, SourceMap, Type, TypeRestriction,
+};
+
+mod manifest;
+
+pub use manifest::{find_manifest, Manifest, ManifestError, MANIFEST_FILE_NAME};
 
 pub type Span = std::ops::Range<usize>;
 pub type Spanned<T> = (T, Span);
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Token {
+    Model,
+    Schema,
+    Module,
+    Extend,
     Type,
     Relations,
     SelfRef,
@@ -18,14 +29,39 @@ pub enum Token {
     As,
     But,
     Not,
+    With,
+    Condition,
     OpenParenthesis,
     CloseParenthesis,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Hash,
+    Colon,
+    Asterisk,
+    LessThan,
+    GreaterThan,
     Identifier(String),
+    Version(String),
+    Expression(String),
+    /// `//` comment including the slashes, it is trivia and skipped by the
+    /// parser
+    Comment(String),
+}
+
+impl Token {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Token::Comment(_))
+    }
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Token::Model => write!(f, "model"),
+            Token::Schema => write!(f, "schema"),
+            Token::Module => write!(f, "module"),
+            Token::Extend => write!(f, "extend"),
             Token::Type => write!(f, "type"),
             Token::Relations => write!(f, "relations"),
             Token::SelfRef => write!(f, "self"),
@@ -36,17 +72,38 @@ impl fmt::Display for Token {
             Token::As => write!(f, "as"),
             Token::But => write!(f, "but"),
             Token::Not => write!(f, "not"),
+            Token::With => write!(f, "with"),
+            Token::Condition => write!(f, "condition"),
             Token::OpenParenthesis => write!(f, "("),
             Token::CloseParenthesis => write!(f, ")"),
+            Token::OpenBracket => write!(f, "["),
+            Token::CloseBracket => write!(f, "]"),
+            Token::Comma => write!(f, ","),
+            Token::Hash => write!(f, "#"),
+            Token::Colon => write!(f, ":"),
+            Token::Asterisk => write!(f, "*"),
+            Token::LessThan => write!(f, "<"),
+            Token::GreaterThan => write!(f, ">"),
             Token::Identifier(s) => write!(f, "{}", s),
+            Token::Version(s) => write!(f, "{}", s),
+            Token::Expression(s) => write!(f, "{{{}}}", s),
+            Token::Comment(s) => write!(f, "{}", s),
         }
     }
 }
 
 pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
-    let ctrl = one_of("()").map(|c| match c {
+    let ctrl = one_of("()[],#:*<>").map(|c| match c {
         '(' => Token::OpenParenthesis,
         ')' => Token::CloseParenthesis,
+        '[' => Token::OpenBracket,
+        ']' => Token::CloseBracket,
+        ',' => Token::Comma,
+        '#' => Token::Hash,
+        ':' => Token::Colon,
+        '*' => Token::Asterisk,
+        '<' => Token::LessThan,
+        '>' => Token::GreaterThan,
         _ => panic!("IMPOSSIBLE!"),
     });
 
@@ -68,26 +125,171 @@ pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
         })
         .labelled("token");
 
+    let version = text::int(10)
+        .then_ignore(just('.'))
+        .then(text::int(10))
+        .map(|(major, minor)| Token::Version(format!("{}.{}", major, minor)))
+        .labelled("version");
+
+    // The CEL expression of a condition is kept as raw text, braces inside of
+    // it only need to be balanced.
+    let expression = recursive(|body| {
+        none_of("{}")
+            .repeated()
+            .at_least(1)
+            .collect::<String>()
+            .or(body
+                .delimited_by(just('{'), just('}'))
+                .map(|inner: String| format!("{{{}}}", inner)))
+            .repeated()
+            .map(|parts: Vec<String>| parts.concat())
+    })
+    .delimited_by(just('{'), just('}'))
+    .map(|body: String| Token::Expression(dedent(&body)))
+    .labelled("expression");
+
     let comment = just("//")
-        .then(take_until(just('\n')))
-        .padded()
+        .ignore_then(none_of('\n').repeated().collect::<String>())
+        .map(|comment| Token::Comment(format!("//{}", comment.trim_end())))
         .labelled("comment");
 
-    let token = ctrl.or(ident);
+    // Characters which don't start a token are skipped, so the rest of the
+    // source is still lexed
+    let token = ctrl
+        .or(expression)
+        .or(version)
+        .or(comment)
+        .or(ident)
+        .recover_with(skip_then_retry_until([]));
 
     token
         .map_with_span(|tok, span| (tok, span))
-        .padded_by(comment.repeated())
         .padded()
         .repeated()
         .then_ignore(end())
+        .map(contextual_keywords)
+}
+
+/// Turns the words `model`, `schema`, `module`, `extend`, `condition` and
+/// `with` into keywords where they start a header, definition or condition
+/// of a type restriction. Everywhere else they stay identifiers, so models
+/// of the 1.0 syntax can keep using them as names.
+fn contextual_keywords(mut tokens: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+    let significant: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, (token, _))| !token.is_trivia())
+        .map(|(i, _)| i)
+        .collect();
+    let mut brackets = 0;
+    for k in 0..significant.len() {
+        let token_at = |k: usize| significant.get(k).map(|i| &tokens[*i].0);
+        let previous = k.checked_sub(1).and_then(token_at);
+        let next = token_at(k + 1);
+        let name = match token_at(k) {
+            Some(Token::Identifier(name)) => name.as_str(),
+            Some(Token::OpenBracket) => {
+                brackets += 1;
+                continue;
+            }
+            Some(Token::CloseBracket) => {
+                brackets -= 1;
+                continue;
+            }
+            _ => continue,
+        };
+        // Definitions can't start where a name is expected, the next token is
+        // missing while the definition is still being typed
+        let definition_start = !matches!(
+            previous,
+            Some(
+                Token::Module
+                    | Token::Type
+                    | Token::Define
+                    | Token::Condition
+                    | Token::Colon
+                    | Token::As
+                    | Token::Or
+                    | Token::And
+                    | Token::Not
+                    | Token::From
+                    | Token::OpenParenthesis
+                    | Token::OpenBracket
+                    | Token::Comma
+                    | Token::Hash
+                    | Token::With
+            )
+        );
+        let keyword = match name {
+            // Models of the 1.0 syntax start with a type
+            "model" if k == 0 => Token::Model,
+            "module" if k == 0 => Token::Module,
+            "schema" if previous == Some(&Token::Model) => Token::Schema,
+            "extend" if definition_start && matches!(next, None | Some(Token::Type)) => {
+                Token::Extend
+            }
+            // A name followed by parameters can only be a condition, even
+            // where the previous definition is still missing a name
+            "condition"
+                if matches!(
+                    (next, token_at(k + 2)),
+                    (Some(Token::Identifier(_)), Some(Token::OpenParenthesis))
+                ) || definition_start
+                    && matches!(next, None | Some(Token::Identifier(_)))
+                    && matches!(token_at(k + 2), None | Some(Token::OpenParenthesis)) =>
+            {
+                Token::Condition
+            }
+            // Within a restriction the type is followed by `:*`, `#` or
+            // `with`, or the restriction ends
+            "with"
+                if brackets > 0
+                    && matches!(previous, Some(Token::Identifier(_) | Token::Asterisk)) =>
+            {
+                Token::With
+            }
+            _ => continue,
+        };
+        tokens[significant[k]].0 = keyword;
+    }
+    tokens
+}
+
+/// Returns true if the name lexes into a single identifier, so it can name a
+/// type, relation or condition.
+pub fn is_identifier(name: &str) -> bool {
+    match lexer().parse(name) {
+        Ok(tokens) => matches!(tokens.as_slice(), [(Token::Identifier(ident), _)] if ident == name),
+        Err(_) => false,
+    }
+}
+
+/// Header of a model, either the `model` header with its schema version or
+/// the `module` header of a file of a modular model.
+#[derive(Clone)]
+enum Header {
+    Schema(String),
+    Module(Identifier),
+}
+
+#[derive(Clone)]
+enum Definition {
+    Type(Type),
+    Condition(Condition),
 }
 
-pub fn better_parser() -> impl Parser<Token, Vec<Type>, Error = Simple<Token>> + Clone {
+/// Returns the parser of the model, the spans of its nodes point into the
+/// given file.
+#[allow(clippy::result_large_err)]
+pub fn better_parser(
+    file: FileId,
+) -> impl Parser<Token, AuthorizationModel, Error = Simple<Token>> + Clone {
+    let node_span = move |span: Span| Some(openfga_common::Span::new(file, span));
+
     let ident = select! { Token::Identifier(ident) => ident.clone() }
-        .map_with_span(|name, span| Identifier {
+        .map_with_span(move |name, span| Identifier {
             name,
-            span: Some(span),
+            span: node_span(span),
         })
         .labelled("identifier");
 
@@ -95,24 +297,60 @@ pub fn better_parser() -> impl Parser<Token, Vec<Type>, Error = Simple<Token>> +
         let parenthesis_access =
             access.delimited_by(just(Token::OpenParenthesis), just(Token::CloseParenthesis));
 
+        // A wildcard stands for every object of the type, so it can't be
+        // followed by a relation like a userset
+        let wildcard = just(Token::Colon)
+            .then(just(Token::Asterisk))
+            .to((true, None));
+        let userset = just(Token::Hash)
+            .ignore_then(ident)
+            .map(|relation| (false, Some(relation)));
+        let type_restriction = ident
+            .then(wildcard.or(userset).or_not())
+            .then(just(Token::With).ignore_then(ident).or_not())
+            .map_with_span(move |((type_identifier, subject), condition), span| {
+                let (wildcard, relation) = subject.unwrap_or((false, None));
+                TypeRestriction {
+                    type_identifier,
+                    relation,
+                    wildcard,
+                    condition,
+                    span: node_span(span),
+                }
+            })
+            .labelled("type restriction");
+
+        let restricted_direct_access = type_restriction
+            .separated_by(just(Token::Comma))
+            .at_least(1)
+            .delimited_by(just(Token::OpenBracket), just(Token::CloseBracket))
+            .map_with_span(move |restrictions, span| Access::Direct {
+                restrictions,
+                span: node_span(span),
+            });
+
         let direct_access = just(Token::SelfRef)
-            .map_with_span(|_, span| Access::Direct { span: Some(span) })
+            .map_with_span(move |_, span| Access::Direct {
+                restrictions: Vec::new(),
+                span: node_span(span),
+            })
+            .or(restricted_direct_access)
             .labelled("direct access");
 
         let computed_self_access = ident
-            .map_with_span(|relation, span| Access::SelfComputed {
+            .map_with_span(move |relation, span| Access::SelfComputed {
                 relation,
-                span: Some(span),
+                span: node_span(span),
             })
             .labelled("computed self access");
 
         let computed_relation_access = ident
             .then_ignore(just(Token::From))
             .then(ident)
-            .map_with_span(|(relation, object), span| Access::Computed {
+            .map_with_span(move |(relation, object), span| Access::Computed {
                 object,
                 relation,
-                span: Some(span),
+                span: node_span(span),
             })
             .labelled("computed relation access");
 
@@ -128,13 +366,13 @@ pub fn better_parser() -> impl Parser<Token, Vec<Type>, Error = Simple<Token>> +
             .separated_by(just(Token::But).then(just(Token::Not)))
             .at_least(1)
             .at_most(2)
-            .map_with_span(|accesses: Vec<Access>, span: Range<usize>| {
+            .map_with_span(move |accesses: Vec<Access>, span: Range<usize>| {
                 accesses
                     .into_iter()
                     .reduce(|prev, current| Access::Difference {
                         base: Box::new(prev),
                         subtract: Box::new(current),
-                        span: Some(span.clone()),
+                        span: node_span(span.clone()),
                     })
                     .unwrap()
             })
@@ -143,60 +381,197 @@ pub fn better_parser() -> impl Parser<Token, Vec<Type>, Error = Simple<Token>> +
         let and_access = difference_access
             .separated_by(just(Token::And))
             .at_least(1)
-            .map_with_span(|mut accesses, span| {
+            .map_with_span(move |mut accesses, span| {
                 if accesses.len() == 1 {
                     return accesses.pop().unwrap();
                 }
                 Access::Intersection {
                     children: accesses,
-                    span: Some(span),
+                    span: node_span(span),
                 }
             })
             .labelled("and");
 
-        let or_access = and_access
+        and_access
             .separated_by(just(Token::Or))
             .at_least(1)
-            .map_with_span(|mut accesses, span| {
+            .map_with_span(move |mut accesses, span| {
                 if accesses.len() == 1 {
                     return accesses.pop().unwrap();
                 }
                 Access::Union {
                     children: accesses,
-                    span: Some(span),
+                    span: node_span(span),
                 }
             })
-            .labelled("or");
-
-        return or_access;
+            .labelled("or")
     });
 
+    // Every definition has to be followed by the start of the next one, so
+    // trailing garbage is reported as part of the broken definition
+    let relation_end = choice((
+        just(Token::Define).ignored(),
+        just(Token::Type).ignored(),
+        just(Token::Extend).ignored(),
+        just(Token::Condition).ignored(),
+        end(),
+    ))
+    .rewind();
+
+    let definition_end = choice((
+        just(Token::Type).ignored(),
+        just(Token::Extend).ignored(),
+        just(Token::Condition).ignored(),
+        end(),
+    ))
+    .rewind();
+
     let relation = just(Token::Define)
         .ignore_then(ident)
-        .then_ignore(just(Token::As))
+        .then_ignore(just(Token::As).or(just(Token::Colon)))
         .then(access)
-        .map_with_span(|(name, access), span| Relation {
+        .then_ignore(relation_end)
+        .map_with_span(move |(name, access), span| Relation {
             identifier: name,
-            access: access,
-            span: Some(span),
+            access,
+            comments: Comments::default(),
+            span: node_span(span),
         })
         .labelled("relation");
 
+    // A broken relation is skipped up to the next definition, keeping the
+    // other relations of the type
     let relations = just(Token::Relations)
-        .ignore_then(relation.repeated())
+        .ignore_then(
+            just(Token::Define)
+                .rewind()
+                .ignore_then(
+                    relation.map(Some).recover_with(
+                        skip_until(
+                            [Token::Define, Token::Type, Token::Extend, Token::Condition],
+                            |_| None,
+                        )
+                        .skip_start(),
+                    ),
+                )
+                .repeated(),
+        )
+        .map(|relations| relations.into_iter().flatten().collect())
         .labelled("relations");
 
-    let typep = just(Token::Type)
-        .ignore_then(ident)
-        .then(relations)
-        .map_with_span(|(ident, relations), span| Type {
+    let typep = just(Token::Extend)
+        .or_not()
+        .then_ignore(just(Token::Type))
+        .then(ident)
+        .then(relations.or_not())
+        .then_ignore(definition_end.clone())
+        .map_with_span(move |((extend, ident), relations), span| Type {
             identifier: ident,
-            relations,
-            span: Some(span),
+            relations: relations.unwrap_or_default(),
+            extension: extend.is_some(),
+            comments: Comments::default(),
+            span: node_span(span),
         })
         .labelled("type");
 
-    typep.repeated().then_ignore(end())
+    let version = select! { Token::Version(version) => version.clone() }.labelled("version");
+
+    let header = just(Token::Model)
+        .ignore_then(just(Token::Schema))
+        .ignore_then(version)
+        .map(Header::Schema)
+        .labelled("model header")
+        .or(just(Token::Module)
+            .ignore_then(ident)
+            .map(Header::Module)
+            .labelled("module header"));
+
+    let parameter_type = recursive(|parameter_type| {
+        ident
+            .then(
+                parameter_type
+                    .delimited_by(just(Token::LessThan), just(Token::GreaterThan))
+                    .or_not(),
+            )
+            .map_with_span(move |(identifier, generic), span| ParameterType {
+                identifier,
+                generic: generic.map(Box::new),
+                span: node_span(span),
+            })
+    })
+    .labelled("parameter type");
+
+    let parameter = ident
+        .then_ignore(just(Token::Colon))
+        .then(parameter_type)
+        .map_with_span(
+            move |(identifier, parameter_type), span| ConditionParameter {
+                identifier,
+                parameter_type,
+                span: node_span(span),
+            },
+        )
+        .labelled("parameter");
+
+    let expression =
+        select! { Token::Expression(expression) => expression.clone() }.labelled("expression");
+
+    let condition = just(Token::Condition)
+        .ignore_then(ident)
+        .then(
+            parameter
+                .separated_by(just(Token::Comma))
+                .delimited_by(just(Token::OpenParenthesis), just(Token::CloseParenthesis)),
+        )
+        .then(expression)
+        .then_ignore(definition_end)
+        .map_with_span(
+            move |((identifier, parameters), expression), span| Condition {
+                identifier,
+                parameters,
+                expression,
+                comments: Comments::default(),
+                span: node_span(span),
+            },
+        )
+        .labelled("condition");
+
+    // A broken definition, or anything else which doesn't start one, is
+    // skipped up to the next type or condition
+    let definition = typep
+        .map(|t| Some(Definition::Type(t)))
+        .or(condition.map(|c| Some(Definition::Condition(c))))
+        .recover_with(
+            skip_until([Token::Type, Token::Extend, Token::Condition], |_| None).skip_start(),
+        );
+
+    header
+        .or_not()
+        .then(definition.repeated())
+        .then_ignore(end())
+        .map(|(header, definitions)| {
+            let (schema_version, module) = match header {
+                Some(Header::Schema(version)) => (Some(version), None),
+                Some(Header::Module(module)) => (None, Some(module)),
+                None => (None, None),
+            };
+            let mut types = Vec::new();
+            let mut conditions = Vec::new();
+            for definition in definitions.into_iter().flatten() {
+                match definition {
+                    Definition::Type(t) => types.push(t),
+                    Definition::Condition(c) => conditions.push(c),
+                }
+            }
+            AuthorizationModel {
+                schema_version,
+                module,
+                types,
+                conditions,
+                header_comments: Comments::default(),
+                end_comments: Vec::new(),
+            }
+        })
 }
 
 #[derive(Debug)]
@@ -205,48 +580,742 @@ pub enum ParseErrors {
     Parser(Vec<Simple<Token>>),
 }
 
-pub fn parse_model(
+impl ParseErrors {
+    /// Returns the span and a plain text message of every error.
+    pub fn messages(&self) -> Vec<(Span, String)> {
+        match self {
+            ParseErrors::Lexer(errs) => errs
+                .iter()
+                .map(|e| {
+                    let message = match e.found() {
+                        Some(c) => format!("Found unexpected character {}", c),
+                        None => "Found unexpected end of input".to_string(),
+                    };
+                    (e.span(), message)
+                })
+                .collect(),
+            ParseErrors::Parser(errs) => errs.iter().map(|e| (e.span(), message(e))).collect(),
+        }
+    }
+}
+
+/// Removes the indentation the lines of the expression have in common, along
+/// with the surrounding whitespace, keeping the indentation of the lines
+/// relative to each other.
+fn dedent(expression: &str) -> String {
+    let indent = expression
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+    expression
+        .lines()
+        .map(|line| line.chars().skip(indent).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn message(e: &Simple<Token>) -> String {
+    if let SimpleReason::Custom(message) = e.reason() {
+        return message.clone();
+    }
+    let found = match e.found() {
+        Some(token) => format!("Found unexpected token {}", token),
+        None => "Found unexpected end of input".to_string(),
+    };
+    let label = match e.label() {
+        Some(label) => format!(" while parsing {}", label),
+        None => String::new(),
+    };
+    let mut expected: Vec<String> = e
+        .expected()
+        .map(|expected| match expected {
+            Some(token) => token.to_string(),
+            None => "end of input".to_string(),
+        })
+        .collect();
+    expected.sort();
+    if expected.is_empty() {
+        expected.push("something else".to_string());
+    }
+    format!(
+        "{}{}, expected instead {}",
+        found,
+        label,
+        expected.join(", ")
+    )
+}
+
+/// Node of the model comments can be attached to.
+enum Anchor {
+    Header,
+    Type(usize),
+    Relation(usize, usize),
+    Condition(usize),
+}
+
+fn anchor_comments<'a>(model: &'a mut AuthorizationModel, anchor: &Anchor) -> &'a mut Comments {
+    match anchor {
+        Anchor::Header => &mut model.header_comments,
+        Anchor::Type(t) => &mut model.types[*t].comments,
+        Anchor::Relation(t, r) => &mut model.types[*t].relations[*r].comments,
+        Anchor::Condition(c) => &mut model.conditions[*c].comments,
+    }
+}
+
+/// Attaches the comment tokens to the nodes of the model. Comments inside of
+/// a node or on the line it ends on trail it, all others lead the next node.
+/// The line of a type ends with its name, as its relations are nodes of their
+/// own.
+fn attach_comments(
+    model: &mut AuthorizationModel,
+    tokens: &[Spanned<Token>],
     src: &str,
-) -> Result<(AuthorizationModel, Vec<(Token, Range<usize>)>), ParseErrors> {
-    let (tokens, errors) = lexer().parse_recovery_verbose(src.trim());
-    if tokens.is_none() {
-        return Err(ParseErrors::Lexer(errors));
+    file: FileId,
+) {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(
+            src.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '\n')
+                .map(|(i, _)| i + 1),
+        )
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|start| *start <= offset);
+
+    let mut anchors: Vec<(Span, Anchor)> = Vec::new();
+    if model.schema_version.is_some() {
+        let start = tokens.iter().find(|(t, _)| *t == Token::Model);
+        let end = tokens.iter().find(|(t, _)| matches!(t, Token::Version(_)));
+        if let (Some((_, start)), Some((_, end))) = (start, end) {
+            anchors.push((start.start..end.end, Anchor::Header));
+        }
     }
+    if let Some(span) = model.module.as_ref().and_then(|m| m.span.as_ref()) {
+        if let Some((_, start)) = tokens.iter().find(|(t, _)| *t == Token::Module) {
+            anchors.push((start.start..span.end, Anchor::Header));
+        }
+    }
+    for (i, t) in model.types.iter().enumerate() {
+        if let (Some(span), Some(identifier)) = (&t.span, &t.identifier.span) {
+            anchors.push((span.start..identifier.end, Anchor::Type(i)));
+        }
+        for (j, r) in t.relations.iter().enumerate() {
+            if let Some(span) = &r.span {
+                anchors.push((span.range(), Anchor::Relation(i, j)));
+            }
+        }
+    }
+    for (i, c) in model.conditions.iter().enumerate() {
+        if let Some(span) = &c.span {
+            anchors.push((span.range(), Anchor::Condition(i)));
+        }
+    }
+    anchors.sort_by_key(|(span, _)| span.start);
+
+    for (token, span) in tokens {
+        let comment = match token {
+            Token::Comment(text) => Comment {
+                text: text.clone(),
+                span: Some(openfga_common::Span::new(file, span.clone())),
+            },
+            _ => continue,
+        };
+        let next = anchors.partition_point(|(anchor, _)| anchor.start <= span.start);
+        match next.checked_sub(1).map(|i| &anchors[i]) {
+            Some((previous, anchor))
+                if span.start < previous.end || line_of(span.start) == line_of(previous.end) =>
+            {
+                anchor_comments(model, anchor).trailing.push(comment)
+            }
+            _ => match anchors.get(next) {
+                Some((_, anchor)) => anchor_comments(model, anchor).leading.push(comment),
+                None => model.end_comments.push(comment),
+            },
+        }
+    }
+}
+
+pub fn parse_model(src: &str) -> Result<(AuthorizationModel, Vec<Spanned<Token>>), ParseErrors> {
+    match parse_model_recovery(src) {
+        (Some(model), tokens, None) => Ok((model, tokens)),
+        (_, _, Some(errors)) => Err(errors),
+        (None, _, None) => unreachable!("parser failed without an error"),
+    }
+}
+
+/// Parses the model, skipping over broken definitions instead of failing.
+/// Returns the part of the model and the tokens which could be recovered,
+/// along with the errors of the source. Lexer errors take precedence over
+/// parser errors, as the latter usually follow from them.
+pub fn parse_model_recovery(
+    src: &str,
+) -> (
+    Option<AuthorizationModel>,
+    Vec<Spanned<Token>>,
+    Option<ParseErrors>,
+) {
+    parse_file_recovery(src, 0)
+}
+
+/// Parses the source like `parse_model_recovery`, with the spans of the
+/// model pointing into the given file.
+pub fn parse_file_recovery(
+    src: &str,
+    file: FileId,
+) -> (
+    Option<AuthorizationModel>,
+    Vec<Spanned<Token>>,
+    Option<ParseErrors>,
+) {
+    let (tokens, lexer_errors) = lexer().parse_recovery_verbose(src);
+    let tokens = tokens.unwrap_or_default();
     let len = src.chars().count();
-    let (types, errs) = better_parser().parse_recovery_verbose(Stream::from_iter(
+    let (mut model, parser_errors) = better_parser(file).parse_recovery_verbose(Stream::from_iter(
         len..len + 1,
-        tokens.clone().unwrap().into_iter(),
+        tokens.iter().filter(|(t, _)| !t.is_trivia()).cloned(),
     ));
-    if types.is_none() {
-        return Err(ParseErrors::Parser(errs));
-    }
-    Ok((
-        AuthorizationModel {
-            types: types.unwrap(),
-        },
-        tokens.unwrap(),
+    if let Some(model) = &mut model {
+        attach_comments(model, &tokens, src, file);
+    }
+    let errors = if !lexer_errors.is_empty() {
+        Some(ParseErrors::Lexer(lexer_errors))
+    } else if !parser_errors.is_empty() {
+        Some(ParseErrors::Parser(parser_errors))
+    } else {
+        None
+    };
+    (model, tokens, errors)
+}
+
+/// Parses the files of a modular model and merges them into a single model,
+/// the spans of its nodes point into the files of the source map. Returns the
+/// errors of every file which can't be parsed instead.
+pub fn parse_modules(
+    schema_version: &str,
+    sources: &SourceMap,
+) -> Result<AuthorizationModel, Vec<(FileId, ParseErrors)>> {
+    let mut models = Vec::new();
+    let mut errors = Vec::new();
+    for (file, source) in sources.files().iter().enumerate() {
+        match parse_file_recovery(&source.src, file) {
+            (Some(model), tokens, None) => {
+                if model.module.is_some() {
+                    models.push(model);
+                    continue;
+                }
+                let span = tokens
+                    .iter()
+                    .find(|(t, _)| !t.is_trivia())
+                    .map_or(0..0, |(_, span)| span.clone());
+                let error = Simple::custom(
+                    span,
+                    "Files of a modular model have to start with a module header",
+                );
+                errors.push((file, ParseErrors::Parser(vec![error])));
+            }
+            (_, _, Some(file_errors)) => errors.push((file, file_errors)),
+            (None, _, None) => unreachable!("parser failed without an error"),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(AuthorizationModel::merge(
+        schema_version.to_string(),
+        models,
     ))
 }
 
 #[cfg(test)]
 mod tests {
-    // #[test]
-    // fn parses_type() {
-    //     let src = concat!(
-    //         "type test\n",
-    //         "    relations\n",
-    //         "       define test as self\n"
-    //     );
-    //     let out = parse_model(src.trim()).unwrap();
-    //     assert_eq!(
-    //         out,
-    //         vec![Type {
-    //             name: String::from("test"),
-    //             relations: vec![Relation {
-    //                 name: String::from("test"),
-    //                 access: Access::Direct
-    //             }]
-    //         }]
-    //     );
-    // }
+    use super::*;
+    use openfga_common::json::AuthorizationModel as JsonAuthModel;
+
+    /// Decompiles the json model and parses the dsl again, returning the json
+    /// of the parsed model.
+    fn decompile_and_parse(json: &str) -> JsonAuthModel {
+        let json_model: JsonAuthModel = serde_json::from_str(json).unwrap();
+        let dsl = AuthorizationModel::from(json_model).to_string();
+        let (model, _) = parse_model(&dsl).unwrap_or_else(|e| panic!("{:?}\n{}", e, dsl));
+        model.into()
+    }
+
+    /// Compiles the dsl to json, decompiles it and parses the result again,
+    /// returning the json of both models.
+    fn round_trip(src: &str) -> (JsonAuthModel, JsonAuthModel) {
+        let (model, _) = parse_model(src).unwrap();
+        let json: JsonAuthModel = model.into();
+        let json_src = serde_json::to_string(&json).unwrap();
+        (json, decompile_and_parse(&json_src))
+    }
+
+    fn names(model: &AuthorizationModel) -> Vec<(String, Vec<String>)> {
+        model
+            .types
+            .iter()
+            .map(|t| {
+                (
+                    t.identifier.name.clone(),
+                    t.relations
+                        .iter()
+                        .map(|r| format!("{}: {}", r.identifier.name, r.access))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_legacy_syntax() {
+        let src = "type user
+type doc
+  relations
+    define owner as self
+    define viewer as self or owner or viewer from parent
+    define parent as self
+";
+        let (model, _) = parse_model(src).unwrap();
+        assert!(model.is_legacy_schema());
+        assert_eq!(
+            names(&model),
+            vec![
+                (String::from("user"), vec![]),
+                (
+                    String::from("doc"),
+                    vec![
+                        String::from("owner: self"),
+                        String::from("viewer: self or owner or viewer from parent"),
+                        String::from("parent: self"),
+                    ]
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_contextual_keywords_as_names_in_legacy_syntax() {
+        let src = "type module
+  relations
+    define condition as self
+    define model as self
+    define with as condition or model but not extend
+    define extend as self
+    define schema as extend from condition
+type extend
+  relations
+    define module as self
+";
+        let (model, _) = parse_model(src).unwrap();
+        assert!(model.is_legacy_schema());
+        assert!(model.conditions.is_empty());
+        assert_eq!(
+            names(&model),
+            vec![
+                (
+                    String::from("module"),
+                    vec![
+                        String::from("condition: self"),
+                        String::from("model: self"),
+                        String::from("with: condition or (model but not extend)"),
+                        String::from("extend: self"),
+                        String::from("schema: extend from condition"),
+                    ]
+                ),
+                (String::from("extend"), vec![String::from("module: self")]),
+            ]
+        );
+        assert!(!model.types[1].extension);
+    }
+
+    #[test]
+    fn parses_schema_1_1_syntax() {
+        let src = "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user, user:*, group#member with in_group]
+type doc
+  relations
+    define parent: [group]
+    define viewer: [user with in_hours] or member from parent
+condition in_hours(hour: int, days: list<string>) {
+  hour > 9
+}
+condition in_group(group: string) {
+  group != \"\"
+}
+";
+        let (model, _) = parse_model(src).unwrap();
+        assert_eq!(model.schema_version.as_deref(), Some("1.1"));
+        assert!(!model.is_legacy_schema());
+        assert_eq!(
+            names(&model)[1..],
+            [
+                (
+                    String::from("group"),
+                    vec![String::from(
+                        "member: [user, user:*, group#member with in_group]"
+                    )]
+                ),
+                (
+                    String::from("doc"),
+                    vec![
+                        String::from("parent: [group]"),
+                        String::from("viewer: [user with in_hours] or member from parent"),
+                    ]
+                ),
+            ]
+        );
+        let conditions: Vec<String> = model.conditions.iter().map(|c| c.to_string()).collect();
+        assert_eq!(model.conditions.len(), 2);
+        assert_eq!(
+            model.conditions[0].parameters[1].parameter_type.to_string(),
+            "list<string>"
+        );
+        assert!(conditions[0].starts_with("condition in_hours(hour: int, days: list<string>)"));
+        assert_eq!(model.conditions[1].expression, "group != \"\"");
+    }
+
+    #[test]
+    fn parses_contextual_keywords_as_names_in_schema_1_1_syntax() {
+        let src = "model
+  schema 1.1
+type with
+type condition
+  relations
+    define model: [with, with:* with extend, condition#schema]
+    define schema: [with]
+    define extend: model or schema
+condition extend(module: string) {
+  module == \"x\"
+}
+";
+        let (model, _) = parse_model(src).unwrap();
+        assert_eq!(
+            names(&model)[1].1,
+            vec![
+                String::from("model: [with, with:* with extend, condition#schema]"),
+                String::from("schema: [with]"),
+                String::from("extend: model or schema"),
+            ]
+        );
+        assert_eq!(model.conditions[0].identifier.name, "extend");
+        assert_eq!(model.conditions[0].parameters[0].identifier.name, "module");
+    }
+
+    #[test]
+    fn parses_modules() {
+        let src = "module tracker
+extend type organization
+  relations
+    define can_create_project: admin
+type project
+  relations
+    define organization: [organization]
+";
+        let (model, _) = parse_model(src).unwrap();
+        assert_eq!(model.module.as_ref().unwrap().name, "tracker");
+        assert_eq!(model.schema_version, None);
+        assert!(model.types[0].extension);
+        assert!(!model.types[1].extension);
+    }
+
+    #[test]
+    fn decompiles_legacy_models() {
+        let json = r#"{
+            "type_definitions": [
+                { "type": "user" },
+                { "type": "doc", "relations": { "viewer": { "this": {} } } }
+            ]
+        }"#;
+        let expected: JsonAuthModel = serde_json::from_str(json).unwrap();
+        assert_eq!(decompile_and_parse(json), expected);
+    }
+
+    #[test]
+    fn decompiles_type_restrictions_without_schema_version() {
+        let json = r#"{
+            "type_definitions": [
+                { "type": "user" },
+                {
+                    "type": "doc",
+                    "relations": { "viewer": { "this": {} } },
+                    "metadata": {
+                        "relations": {
+                            "viewer": { "directly_related_user_types": [{ "type": "user" }] }
+                        }
+                    }
+                }
+            ]
+        }"#;
+        let parsed = decompile_and_parse(json);
+        let mut expected: JsonAuthModel = serde_json::from_str(json).unwrap();
+        expected.schema_version = Some(String::from("1.1"));
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn rejects_wildcards_with_a_relation() {
+        let src = "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user:*#member]
+";
+        let errors = parse_model(src).err().unwrap().messages();
+        assert_eq!(errors.len(), 1);
+        // The relation behind the wildcard is unexpected
+        let hash = src.find('#').unwrap();
+        assert_eq!(errors[0].0, hash..hash + 1);
+    }
+
+    #[test]
+    fn round_trips_type_restrictions() {
+        let (json, parsed) = round_trip(
+            "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user, user:*, group#member]
+    define admin: [user] or member
+",
+        );
+        assert_eq!(parsed, json);
+    }
+
+    #[test]
+    fn recovers_from_broken_type_restrictions() {
+        let src = "model
+  schema 1.1
+type user
+type group
+  relations
+    define member: [user,]
+    define admin: [user]
+type doc
+  relations
+    define viewer: [group#member]
+";
+        let (model, _, errors) = parse_model_recovery(src);
+        assert!(matches!(errors, Some(ParseErrors::Parser(_))));
+        let model = model.unwrap();
+        let types: Vec<&str> = model
+            .types
+            .iter()
+            .map(|t| t.identifier.name.as_str())
+            .collect();
+        assert_eq!(types, vec!["user", "group", "doc"]);
+        assert_eq!(
+            model.types[2].relations[0].access.to_string(),
+            "[group#member]"
+        );
+    }
+
+    #[test]
+    fn recovers_conditions_after_unfinished_restrictions() {
+        let src = "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [user with
+condition in_hours(hour: int) {
+  hour > 9
+}
+";
+        let (model, _, errors) = parse_model_recovery(src);
+        assert!(matches!(errors, Some(ParseErrors::Parser(_))));
+        let model = model.unwrap();
+        assert_eq!(model.conditions[0].identifier.name, "in_hours");
+    }
+
+    #[test]
+    fn round_trips_conditions() {
+        let (json, parsed) = round_trip(
+            "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [user, user with in_hours, user:* with in_hours]
+condition in_hours(hour: int, days: list<string>, ips: map<ipaddress>) {
+  hour > 9 &&
+    hour < 17
+}
+",
+        );
+        assert_eq!(parsed, json);
+        let condition = &json.conditions["in_hours"];
+        assert_eq!(condition.expression, "hour > 9 &&\n  hour < 17");
+    }
+
+    #[test]
+    fn recovers_from_broken_conditions() {
+        let src = "model
+  schema 1.1
+type user
+type doc
+  relations
+    define viewer: [user with in_hours]
+condition in_hours(hour: ) {
+  hour > 9
+}
+condition in_office(ip: ipaddress) {
+  ip == \"10.0.0.1\"
+}
+";
+        let (model, _, errors) = parse_model_recovery(src);
+        let errors = errors.unwrap().messages();
+        assert_eq!(errors.len(), 1);
+        let model = model.unwrap();
+        assert_eq!(
+            model.types[1].relations[0].access.to_string(),
+            "[user with in_hours]"
+        );
+        let conditions: Vec<&str> = model
+            .conditions
+            .iter()
+            .map(|c| c.identifier.name.as_str())
+            .collect();
+        assert!(conditions.contains(&"in_office"), "{:?}", conditions);
+    }
+
+    fn comment_texts(comments: &[openfga_common::Comment]) -> Vec<&str> {
+        comments.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn attaches_comments_to_definitions() {
+        let src = "// the model
+model
+  schema 1.1 // current version
+// people
+type user
+// documents
+// shared with users
+type doc // a doc
+  relations
+    // who owns it
+    define owner: [user] // one or more
+    define viewer: [user] or owner
+// the end
+";
+        let (model, tokens) = parse_model(src).unwrap();
+        assert_eq!(tokens.iter().filter(|(t, _)| t.is_trivia()).count(), 9);
+        assert_eq!(
+            comment_texts(&model.header_comments.leading),
+            vec!["// the model"]
+        );
+        assert_eq!(
+            comment_texts(&model.header_comments.trailing),
+            vec!["// current version"]
+        );
+        assert_eq!(
+            comment_texts(&model.types[0].comments.leading),
+            vec!["// people"]
+        );
+        let doc = &model.types[1];
+        assert_eq!(
+            comment_texts(&doc.comments.leading),
+            vec!["// documents", "// shared with users"]
+        );
+        assert_eq!(comment_texts(&doc.comments.trailing), vec!["// a doc"]);
+        assert_eq!(
+            comment_texts(&doc.relations[0].comments.leading),
+            vec!["// who owns it"]
+        );
+        assert_eq!(
+            comment_texts(&doc.relations[0].comments.trailing),
+            vec!["// one or more"]
+        );
+        assert!(doc.relations[1].comments.leading.is_empty());
+        assert_eq!(comment_texts(&model.end_comments), vec!["// the end"]);
+    }
+
+    #[test]
+    fn keeps_comments_of_broken_documents() {
+        let src = "model
+  schema 1.1
+// people
+type user
+type doc
+  relations
+    // who owns it
+    define owner: [user] or
+    define viewer: [user]
+";
+        let (model, tokens, errors) = parse_model_recovery(src);
+        assert!(errors.is_some());
+        assert!(tokens
+            .iter()
+            .any(|(t, _)| *t == Token::Comment(String::from("// who owns it"))));
+        let model = model.unwrap();
+        assert_eq!(
+            comment_texts(&model.types[0].comments.leading),
+            vec!["// people"]
+        );
+    }
+
+    const CORE: &str = "module core
+type user
+type organization
+  relations
+    define admin: [user]
+";
+
+    #[test]
+    fn round_trips_modules() {
+        let mut sources = SourceMap::default();
+        sources.add_file("core.openfga", CORE);
+        sources.add_file(
+            "tracker.openfga",
+            "module tracker
+extend type organization
+  relations
+    define can_create_project: admin
+type project
+  relations
+    define organization: [organization]
+",
+        );
+        let model = parse_modules("1.2", &sources).unwrap();
+        assert_eq!(model.types[2].relations[0].span.as_ref().unwrap().file, 1);
+        let json: JsonAuthModel = model.into();
+        let parsed = decompile_and_parse(&serde_json::to_string(&json).unwrap());
+        assert_eq!(parsed, json);
+        assert_eq!(
+            json.type_definitions[1]
+                .relations
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["admin", "can_create_project"]
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors_of_every_module() {
+        let mut sources = SourceMap::default();
+        sources.add_file("core.openfga", CORE);
+        sources.add_file(
+            "broken.openfga",
+            "module broken\ntype project\n  relations\n    define owner: [user\n",
+        );
+        sources.add_file("model.openfga", "model\n  schema 1.1\ntype team\n");
+        let errors = parse_modules("1.2", &sources).err().unwrap();
+        let files: Vec<FileId> = errors.iter().map(|(file, _)| *file).collect();
+        assert_eq!(files, vec![1, 2]);
+        // Broken files still recover as much as they can for the editor
+        let (model, _, errors) = parse_file_recovery(sources.file(1).unwrap().src.as_str(), 1);
+        assert!(errors.is_some());
+        let model = model.unwrap();
+        assert_eq!(model.module.unwrap().name, "broken");
+        assert_eq!(model.types[0].span.as_ref().unwrap().file, 1);
+    }
 }
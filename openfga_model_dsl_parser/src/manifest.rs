@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Name of the manifest of a modular model, it is looked up in the directory
+/// of a module file and all of its parents.
+pub const MANIFEST_FILE_NAME: &str = "fga.mod";
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("Failed to read manifest: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse manifest: {0}")]
+    Syntax(#[from] serde_yaml::Error),
+    #[error("Manifest doesn't list any module file")]
+    NoContents,
+}
+
+/// Manifest of a modular model, written in yaml or json:
+///
+/// ```yaml
+/// schema: '1.2'
+/// contents:
+///   - core.openfga
+///   - tracker/projects.openfga
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Schema version of the merged model
+    pub schema: String,
+    /// Paths of the module files, relative to the manifest
+    pub contents: Vec<PathBuf>,
+}
+
+impl Manifest {
+    pub fn parse(src: &str) -> Result<Manifest, ManifestError> {
+        let manifest: Manifest = serde_yaml::from_str(src)?;
+        if manifest.contents.is_empty() {
+            return Err(ManifestError::NoContents);
+        }
+        Ok(manifest)
+    }
+
+    pub fn load(path: &Path) -> Result<Manifest, ManifestError> {
+        Manifest::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the paths of the module files, joined to the directory of the
+    /// manifest at the given path. The index of a file is the file id of its
+    /// nodes in the merged model.
+    pub fn files(&self, manifest_path: &Path) -> Vec<PathBuf> {
+        let dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+        self.contents.iter().map(|file| dir.join(file)).collect()
+    }
+}
+
+/// Searches the manifest for the module file at the given path, starting in
+/// its directory and walking up to the root. The manifest isn't required to
+/// list the file.
+pub fn find_manifest(path: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let start = if path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent()?
+    };
+    start
+        .ancestors()
+        .map(|dir| dir.join(MANIFEST_FILE_NAME))
+        .find(|path| path.is_file())
+}
@@ -41,8 +41,11 @@ export function activate(context: ExtensionContext) {
     // Register the server for plain text documents
     documentSelector: [{ scheme: "file", language: "openfga" }],
     synchronize: {
-      // Notify the server about file changes to '.openfgarc files contained in the workspace
-      fileEvents: workspace.createFileSystemWatcher("**/.openfgarc"),
+      // Notify the server about file changes to '.openfgarc' and 'fga.mod' files contained in the workspace
+      fileEvents: [
+        workspace.createFileSystemWatcher("**/.openfgarc"),
+        workspace.createFileSystemWatcher("**/fga.mod"),
+      ],
     },
   };
 